@@ -17,4 +17,6 @@ _workset_complete() {
     ))
 }
 
-complete -o bashdefault -o default -F _workset_complete workset
+# The binary orders candidates itself (recently dropped repos first), so bash
+# must not sort them. -o nosort needs bash 4.4 or newer.
+complete -o nosort -o bashdefault -o default -F _workset_complete workset
@@ -5,5 +5,9 @@
 
 # The binary expects COMP_LINE to hold the command line truncated at the
 # cursor; fish filters the returned candidates by the current token itself.
-complete --command workset --no-files \
+# Candidates are already ranked (recently dropped repos first), so keep their
+# order.
+complete --command workset --no-files --keep-order \
     --arguments '(env _ARGCOMPLETE_=fish COMP_LINE=(commandline --cut-at-cursor --current-process) workset)'
+# apply takes a manifest file
+complete --command workset --condition '__fish_seen_subcommand_from apply' --force-files
@@ -0,0 +1,335 @@
+//! Cached repo states.
+//!
+//! Working out a [`RepoState`] means a full status scan of the repo's
+//! worktree, which adds up across a large workspace. `list`, `status`, shell
+//! completions and the TUI keep their results in `.workset/status-cache.json`
+//! so later runs can skip repos that haven't changed.
+//!
+//! Each entry is stored with a [`CacheKey`]: the commit HEAD points at plus
+//! the modification times of the git directory, its ref directories and the
+//! worktree root. Commits, checkouts, staging, fetches, stashes and files
+//! created or deleted at the top of the worktree all change the key. Edits to
+//! existing files don't, so entries also expire after `status.cache_ttl_secs`,
+//! and the TUI rescans repos whenever its file watcher sees them change.
+
+use crate::{RepoState, check_repo_state_and_modification_time, now_millis, write_atomically};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// Name of the cache file inside the workspace's `.workset/` directory
+pub const CACHE_FILE: &str = "status-cache.json";
+
+/// Which cached entries a lookup may use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    /// Scan every repo, replacing whatever was cached
+    Refresh,
+    /// Use entries whose key still matches and that are younger than this
+    Within(Duration),
+    /// Use any entry however old, scanning only repos that have none. For
+    /// shell completions, where answering instantly matters most.
+    Any,
+}
+
+/// Repo states keyed by the repo's workspace-relative path
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StatusCache {
+    pub repos: BTreeMap<String, CacheEntry>,
+    /// Whether entries were added or removed since the cache was loaded
+    #[serde(skip)]
+    changed: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CacheEntry {
+    pub key: CacheKey,
+    pub state: RepoState,
+    /// When the repo was last modified, in seconds since the Unix epoch
+    pub modified: Option<u64>,
+    /// When the repo was scanned, in milliseconds since the Unix epoch
+    pub scanned_at: u64,
+}
+
+/// Cheap signals that change whenever most of a repo's state does
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CacheKey {
+    /// The commit HEAD points at, if there is one
+    head: Option<String>,
+    /// Modification times of the paths in [`CacheKey::watched_paths`], in
+    /// nanoseconds since the Unix epoch (0 when missing)
+    mtimes: Vec<u64>,
+}
+
+impl CacheKey {
+    /// Read the key of the repo at the given path. This opens the repo but
+    /// doesn't look at its worktree files.
+    pub fn read(repo_path: &Path) -> Self {
+        let head = gix::open(repo_path)
+            .ok()
+            .and_then(|repo| repo.head_id().ok().map(|id| id.to_string()));
+        let mtimes = Self::watched_paths(repo_path)
+            .iter()
+            .map(|path| {
+                std::fs::metadata(path)
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |d| d.as_nanos() as u64)
+            })
+            .collect();
+        Self { head, mtimes }
+    }
+
+    /// Paths whose modification time is part of the key. Git replaces files
+    /// by renaming a lock file over them, which touches the directory: the git
+    /// directory covers the index, HEAD, FETCH_HEAD, packed refs and the state
+    /// files of a merge or rebase, and the ref directories cover branches,
+    /// tags, the stash and remote-tracking refs. A linked worktree keeps its
+    /// own HEAD and index but shares refs with its main repo.
+    fn watched_paths(repo_path: &Path) -> Vec<PathBuf> {
+        let git_dir = crate::git_dir(repo_path);
+        let common_dir = crate::common_git_dir(repo_path);
+        let mut paths = vec![repo_path.to_path_buf(), git_dir.clone()];
+        if common_dir != git_dir {
+            paths.push(common_dir.clone());
+        }
+        paths.extend([
+            common_dir.join("refs"),
+            common_dir.join("refs/heads"),
+            common_dir.join("refs/tags"),
+        ]);
+        if let Ok(remotes) = std::fs::read_dir(common_dir.join("refs/remotes")) {
+            let mut remotes: Vec<PathBuf> = remotes.flatten().map(|e| e.path()).collect();
+            remotes.sort();
+            paths.extend(remotes);
+        }
+        paths
+    }
+}
+
+/// A repo's state along with where it came from
+#[derive(Debug, Clone)]
+pub struct CachedState {
+    pub state: RepoState,
+    pub modified: Option<SystemTime>,
+    /// When the repo was scanned
+    pub scanned_at: SystemTime,
+    /// Whether the state was served from the cache rather than scanned now
+    pub cached: bool,
+}
+
+impl CachedState {
+    /// How long ago the repo was scanned
+    pub fn age(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(self.scanned_at)
+            .unwrap_or_default()
+    }
+}
+
+impl StatusCache {
+    /// Read the cache of the workspace at the given root. The cache only
+    /// saves time, so a missing or unreadable file is treated as empty.
+    pub fn load(workspace_root: &Path) -> Self {
+        let path = Self::path(workspace_root);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    debug!(path = %path.display(), error = %e, "Failed to read status cache");
+                }
+                return Self::default();
+            }
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            debug!(path = %path.display(), error = %e, "Discarding invalid status cache");
+            Self::default()
+        })
+    }
+
+    /// Write the cache atomically if anything changed
+    pub fn save(&self, workspace_root: &Path) -> Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        write_atomically(&Self::path(workspace_root), &serde_json::to_string(self)?)
+    }
+
+    fn path(workspace_root: &Path) -> PathBuf {
+        workspace_root.join(".workset").join(CACHE_FILE)
+    }
+
+    /// The entry for a repo, if it's usable at the given freshness. `key` is
+    /// the repo's current key; it's only needed for [`Freshness::Within`].
+    pub fn get(
+        &self,
+        repo: &str,
+        key: Option<&CacheKey>,
+        freshness: Freshness,
+    ) -> Option<&CacheEntry> {
+        let entry = self.repos.get(repo)?;
+        match freshness {
+            Freshness::Refresh => None,
+            Freshness::Any => Some(entry),
+            Freshness::Within(ttl) => {
+                let age = now_millis().saturating_sub(entry.scanned_at);
+                (key == Some(&entry.key) && age < ttl.as_millis() as u64).then_some(entry)
+            }
+        }
+    }
+
+    /// Record the state of a repo. The key must have been read before the
+    /// scan started, so changes made during the scan invalidate the entry.
+    pub fn insert(
+        &mut self,
+        repo: &str,
+        key: CacheKey,
+        state: RepoState,
+        modified: Option<SystemTime>,
+    ) {
+        let modified = modified
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs());
+        self.repos.insert(
+            repo.to_string(),
+            CacheEntry {
+                key,
+                state,
+                modified,
+                scanned_at: now_millis(),
+            },
+        );
+        self.changed = true;
+    }
+
+    /// Forget a repo, e.g. because it changed
+    pub fn invalidate(&mut self, repo: &str) {
+        if self.repos.remove(repo).is_some() {
+            self.changed = true;
+        }
+    }
+
+    /// Forget every repo not in the given set, e.g. ones that were dropped
+    pub fn retain(&mut self, repos: &[String]) {
+        let before = self.repos.len();
+        self.repos.retain(|repo, _| repos.contains(repo));
+        self.changed |= self.repos.len() != before;
+    }
+}
+
+/// The state of one repo, served from the cache when the freshness allows
+/// and scanned (and cached) otherwise. `None` if it can't be opened. The
+/// cache sits behind a lock so worker threads can share it; the lock isn't
+/// held while the repo is read.
+pub fn lookup(
+    cache: &Mutex<StatusCache>,
+    repo: &str,
+    repo_path: &Path,
+    freshness: Freshness,
+) -> Result<Option<CachedState>> {
+    let lock = || cache.lock().unwrap_or_else(|e| e.into_inner());
+    let key = match freshness {
+        Freshness::Any if lock().repos.contains_key(repo) => None,
+        _ => Some(CacheKey::read(repo_path)),
+    };
+    if let Some(entry) = lock().get(repo, key.as_ref(), freshness) {
+        return Ok(Some(entry.into()));
+    }
+
+    let key = key.unwrap_or_else(|| CacheKey::read(repo_path));
+    let (state, modified) = check_repo_state_and_modification_time(repo_path)?;
+    let Some(state) = state else {
+        lock().invalidate(repo);
+        return Ok(None);
+    };
+    lock().insert(repo, key, state.clone(), modified);
+    Ok(Some(CachedState {
+        state,
+        modified,
+        scanned_at: SystemTime::now(),
+        cached: false,
+    }))
+}
+
+impl From<&CacheEntry> for CachedState {
+    fn from(entry: &CacheEntry) -> Self {
+        Self {
+            state: entry.state.clone(),
+            modified: entry
+                .modified
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+            scanned_at: UNIX_EPOCH + Duration::from_millis(entry.scanned_at),
+            cached: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::git;
+    use tempfile::TempDir;
+
+    #[test]
+    fn entries_are_served_until_the_key_changes() {
+        let temp = TempDir::new().unwrap();
+        std::fs::create_dir(temp.path().join(".workset")).unwrap();
+        let repo_path = temp.path().join("app");
+        std::fs::create_dir(&repo_path).unwrap();
+        git(&repo_path, &["init", "-q", "-b", "main"]);
+        git(
+            &repo_path,
+            &["commit", "-q", "--allow-empty", "-m", "initial"],
+        );
+        let ttl = Freshness::Within(Duration::from_secs(60));
+
+        let cached = |cache: &Mutex<StatusCache>, freshness| {
+            lookup(cache, "app", &repo_path, freshness)
+                .unwrap()
+                .unwrap()
+                .cached
+        };
+
+        let cache = Mutex::new(StatusCache::load(temp.path()));
+        assert!(!cached(&cache, ttl));
+        cache.lock().unwrap().save(temp.path()).unwrap();
+
+        let cache = Mutex::new(StatusCache::load(temp.path()));
+        assert!(cached(&cache, ttl));
+        assert!(!cached(&cache, Freshness::Refresh));
+
+        // A commit moves HEAD, so the entry no longer matches
+        git(
+            &repo_path,
+            &["commit", "-q", "--allow-empty", "-m", "second"],
+        );
+        assert!(!cached(&cache, ttl));
+
+        // A new file at the top of the worktree touches the root directory
+        std::fs::write(repo_path.join("new.txt"), "").unwrap();
+        let dirty = lookup(&cache, "app", &repo_path, ttl).unwrap().unwrap();
+        assert!(!dirty.cached);
+        assert_eq!(dirty.state.untracked, 1);
+
+        // Completions take whatever is there, however old
+        let expire = || {
+            cache
+                .lock()
+                .unwrap()
+                .repos
+                .get_mut("app")
+                .unwrap()
+                .scanned_at = 0
+        };
+        expire();
+        assert!(!cached(&cache, ttl));
+        expire();
+        assert!(cached(&cache, Freshness::Any));
+    }
+}
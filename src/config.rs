@@ -0,0 +1,506 @@
+//! Per-workspace settings.
+//!
+//! Settings are read from `.workset/config.toml` when the workspace is loaded.
+//! Every key is optional: a missing file or key falls back to the built-in
+//! default, so existing workspaces keep working unchanged. The file carries a
+//! `version` so the format can evolve without misreading older files.
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The newest config format this build understands
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Name of the config file inside the workspace's `.workset/` directory
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Written by `workset init`; every setting is commented out at its default
+pub const DEFAULT_CONFIG: &str = r#"# workset configuration
+version = 1
+
+# Where dropped repos are kept. Relative paths are resolved against the
+# workspace root and "~" expands to your home directory. Several workspaces
+# can share one library, which may live on a different disk.
+# library = ".workset"
+
+[clone]
+# Protocol used to build clone URLs from repo patterns: "https" or "ssh"
+# protocol = "https"
+
+# Per-provider overrides, either a protocol or a URL template where {provider}
+# and {path} are replaced by the parts of the repo pattern
+# [clone.providers."github.com"]
+# protocol = "ssh"
+# [clone.providers."git.example.com"]
+# url = "ssh://git@git.example.com:2222/{path}.git"
+
+[restore]
+# Fetch every remote after restoring a repo, fast-forwarding the checked-out
+# branch when it's safe (override with --fetch / --no-fetch)
+# fetch = false
+
+[sync]
+# Seconds before a fetch or push to a remote is abandoned
+# network_timeout_secs = 60
+# How many repos the TUI syncs with their remotes at once
+# max_concurrent = 4
+# Seconds between the TUI's periodic checks of every repo's remotes
+# interval_secs = 300
+
+[status]
+# Seconds a cached repo status is trusted by `list` and `status` when nothing
+# git tracks has changed; edits to existing files only show up after this
+# (override with --refresh). Shell completions use the cache however old.
+# cache_ttl_secs = 60
+
+[prune]
+# How long a clean repo must go untouched before `workset prune` drops it
+# (override with --older-than), e.g. "30d", "2w" or "6mo"
+# older_than = "30d"
+# Repo patterns that are never pruned
+# pinned = []
+
+[discovery]
+# How many directories below the workspace root to search for repos
+# max_depth = 10
+# Also look inside repos for independent repos checked out within them
+# (submodules are always shown with their parent)
+# nested = false
+
+[watch]
+# How the TUI notices changes: "full" watches every directory, "repos" only
+# the directories leading to repos plus each repo's root, index, HEAD and
+# remote refs, and "poll" checks every repo now and then. "auto" starts with
+# "full" and falls back when the system runs out of watches.
+# mode = "auto"
+# Seconds between checks when polling
+# poll_interval_secs = 30
+"#;
+
+/// Contents of a new workspace's config file, optionally pointing at a
+/// library outside the workspace
+pub fn initial_config(library: Option<&str>) -> String {
+    match library {
+        Some(library) => DEFAULT_CONFIG.replacen(
+            "# library = \".workset\"",
+            &format!("library = {}", toml::Value::from(library)),
+            1,
+        ),
+        None => DEFAULT_CONFIG.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Config format version
+    pub version: u32,
+    /// Library location, absolute or relative to the workspace root
+    pub library: PathBuf,
+    pub clone: CloneConfig,
+    pub restore: RestoreConfig,
+    pub sync: SyncConfig,
+    pub status: StatusConfig,
+    pub prune: PruneConfig,
+    pub discovery: DiscoveryConfig,
+    pub watch: WatchConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            library: PathBuf::from(".workset"),
+            clone: CloneConfig::default(),
+            restore: RestoreConfig::default(),
+            sync: SyncConfig::default(),
+            status: StatusConfig::default(),
+            prune: PruneConfig::default(),
+            discovery: DiscoveryConfig::default(),
+            watch: WatchConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Read the config file at the given path, falling back to defaults if
+    /// it doesn't exist
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => bail!("Failed to read {}: {}", path.display(), e),
+        };
+        Self::parse(&content).map_err(|e| anyhow::anyhow!("Invalid {}: {}", path.display(), e))
+    }
+
+    /// Parse and validate config file contents
+    pub fn parse(content: &str) -> Result<Self> {
+        let config: Self = toml::from_str(content)?;
+        if config.version == 0 || config.version > CONFIG_VERSION {
+            bail!(
+                "unsupported config version {} (this build supports up to {})",
+                config.version,
+                CONFIG_VERSION
+            );
+        }
+        if config.sync.max_concurrent == 0 {
+            bail!("sync.max_concurrent must be at least 1");
+        }
+        crate::prune::parse_age(&config.prune.older_than)
+            .map_err(|e| anyhow::anyhow!("prune.older_than: {}", e))?;
+        if config.discovery.max_depth == 0 {
+            bail!("discovery.max_depth must be at least 1");
+        }
+        if config.watch.poll_interval_secs == 0 {
+            bail!("watch.poll_interval_secs must be at least 1");
+        }
+        for (provider, settings) in &config.clone.providers {
+            match (&settings.protocol, &settings.url) {
+                (Some(_), Some(_)) => {
+                    bail!("clone.providers.\"{provider}\" can't set both protocol and url")
+                }
+                (_, Some(url)) if !url.contains("{path}") => {
+                    bail!("clone.providers.\"{provider}\".url must contain {{path}}")
+                }
+                _ => {}
+            }
+        }
+        Ok(config)
+    }
+
+    /// Resolve the configured library location for the workspace at the
+    /// given root
+    pub fn library_root(&self, workspace_root: &Path) -> PathBuf {
+        if let Ok(rest) = self.library.strip_prefix("~")
+            && let Some(home) = std::env::var_os("HOME")
+        {
+            return PathBuf::from(home).join(rest);
+        }
+        workspace_root.join(&self.library)
+    }
+
+    /// Build the URL used to clone `path` from `provider`, honoring any
+    /// override configured for the provider
+    pub fn clone_url(&self, provider: &str, path: &str) -> String {
+        let settings = self.clone.providers.get(provider);
+        if let Some(template) = settings.and_then(|s| s.url.as_ref()) {
+            return template
+                .replace("{provider}", provider)
+                .replace("{path}", path);
+        }
+        match settings
+            .and_then(|s| s.protocol)
+            .unwrap_or(self.clone.protocol)
+        {
+            CloneProtocol::Https => format!("https://{}/{}", provider, path),
+            CloneProtocol::Ssh => format!("git@{}:{}.git", provider, path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloneProtocol {
+    #[default]
+    Https,
+    Ssh,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CloneConfig {
+    /// Protocol for providers without an override
+    pub protocol: CloneProtocol,
+    /// Overrides keyed by provider host
+    pub providers: BTreeMap<String, ProviderConfig>,
+}
+
+/// How to clone from one provider. At most one of the fields may be set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProviderConfig {
+    pub protocol: Option<CloneProtocol>,
+    /// URL template containing `{path}` and optionally `{provider}`
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RestoreConfig {
+    /// Fetch and fast-forward repos after restoring them from the library
+    pub fetch: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyncConfig {
+    /// Timeout for git commands that hit the network (fetch, push, ls-remote)
+    pub network_timeout_secs: u64,
+    /// How many repos may sync (fetch/push) concurrently in the TUI
+    pub max_concurrent: usize,
+    /// How often the TUI re-checks all repos against their remotes
+    pub interval_secs: u64,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            network_timeout_secs: 60,
+            max_concurrent: 4,
+            interval_secs: 300,
+        }
+    }
+}
+
+impl SyncConfig {
+    pub fn network_timeout(&self) -> Duration {
+        Duration::from_secs(self.network_timeout_secs)
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusConfig {
+    /// How long `list` and `status` trust a cached repo state
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for StatusConfig {
+    fn default() -> Self {
+        Self { cache_ttl_secs: 60 }
+    }
+}
+
+impl StatusConfig {
+    pub fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.cache_ttl_secs)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PruneConfig {
+    /// Age like "30d" after which a clean repo counts as stale
+    pub older_than: String,
+    /// Patterns of repos that are never pruned
+    pub pinned: Vec<String>,
+}
+
+impl Default for PruneConfig {
+    fn default() -> Self {
+        Self {
+            older_than: "30d".to_string(),
+            pinned: Vec::new(),
+        }
+    }
+}
+
+impl PruneConfig {
+    pub fn older_than(&self) -> Duration {
+        // Validated when the config is parsed
+        crate::prune::parse_age(&self.older_than).unwrap_or(Duration::MAX)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscoveryConfig {
+    /// Deepest directory level below the workspace root searched for repos
+    pub max_depth: usize,
+    /// Whether to search inside repos for other repos (not submodules)
+    pub nested: bool,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 10,
+            nested: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatchConfig {
+    /// How the TUI keeps track of changes in the workspace
+    pub mode: WatchMode,
+    /// Seconds between checks in [`WatchMode::Poll`]
+    pub poll_interval_secs: u64,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            mode: WatchMode::Auto,
+            poll_interval_secs: 30,
+        }
+    }
+}
+
+impl WatchConfig {
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval_secs)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchMode {
+    /// Watch everything, falling back to fewer watches and then to polling
+    /// when the system's watch limit is reached
+    #[default]
+    Auto,
+    /// Watch every directory that isn't ignored
+    Full,
+    /// Watch the directories leading to repos, and each repo's root, index,
+    /// HEAD and remote-tracking refs
+    Repos,
+    /// Check every repo for changes at an interval, without any watches
+    Poll,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_template_matches_defaults() {
+        assert_eq!(Config::parse(DEFAULT_CONFIG).unwrap(), Config::default());
+    }
+
+    #[test]
+    fn partial_config_keeps_other_defaults() {
+        let config = Config::parse(
+            "version = 1\nlibrary = \"/mnt/big/library\"\n[sync]\nmax_concurrent = 8\n",
+        )
+        .unwrap();
+        assert_eq!(config.library, PathBuf::from("/mnt/big/library"));
+        assert_eq!(config.sync.max_concurrent, 8);
+        assert_eq!(config.sync.network_timeout(), Duration::from_secs(60));
+        assert_eq!(config.clone.protocol, CloneProtocol::Https);
+    }
+
+    #[test]
+    fn newer_version_rejected() {
+        let err = Config::parse("version = 99\n").unwrap_err();
+        assert!(err.to_string().contains("unsupported config version 99"));
+    }
+
+    #[test]
+    fn unknown_keys_rejected() {
+        assert!(Config::parse("[sync]\nmax_concurent = 2\n").is_err());
+    }
+
+    #[test]
+    fn initial_config_sets_library() {
+        let config = Config::parse(&initial_config(Some("~/shared library"))).unwrap();
+        assert_eq!(config.library, PathBuf::from("~/shared library"));
+        assert_eq!(
+            Config::parse(&initial_config(None)).unwrap(),
+            Config::default()
+        );
+    }
+
+    #[test]
+    fn library_root_resolution() {
+        let root = Path::new("/ws");
+        let mut config = Config::default();
+        assert_eq!(config.library_root(root), PathBuf::from("/ws/.workset"));
+
+        config.library = PathBuf::from("/mnt/library");
+        assert_eq!(config.library_root(root), PathBuf::from("/mnt/library"));
+
+        config.library = PathBuf::from("~/.workset");
+        let home = PathBuf::from(std::env::var_os("HOME").unwrap());
+        assert_eq!(config.library_root(root), home.join(".workset"));
+    }
+
+    #[test]
+    fn clone_url_follows_protocol() {
+        let mut config = Config::default();
+        assert_eq!(
+            config.clone_url("github.com", "fossable/workset"),
+            "https://github.com/fossable/workset"
+        );
+        config.clone.protocol = CloneProtocol::Ssh;
+        assert_eq!(
+            config.clone_url("github.com", "fossable/workset"),
+            "git@github.com:fossable/workset.git"
+        );
+    }
+
+    #[test]
+    fn clone_url_provider_overrides() {
+        let config = Config::parse(
+            r#"
+            [clone]
+            protocol = "ssh"
+            [clone.providers."github.com"]
+            protocol = "https"
+            [clone.providers."git.example.com"]
+            url = "ssh://git@{provider}:2222/{path}.git"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.clone_url("github.com", "fossable/workset"),
+            "https://github.com/fossable/workset"
+        );
+        assert_eq!(
+            config.clone_url("git.example.com", "team/app"),
+            "ssh://git@git.example.com:2222/team/app.git"
+        );
+        assert_eq!(
+            config.clone_url("gitlab.com", "team/app"),
+            "git@gitlab.com:team/app.git"
+        );
+    }
+
+    #[test]
+    fn prune_settings() {
+        let config =
+            Config::parse("[prune]\nolder_than = \"2w\"\npinned = [\"dotfiles\"]\n").unwrap();
+        assert_eq!(config.prune.older_than(), Duration::from_secs(14 * 86_400));
+        assert_eq!(config.prune.pinned, ["dotfiles"]);
+        assert!(Config::parse("[prune]\nolder_than = \"soon\"\n").is_err());
+    }
+
+    #[test]
+    fn discovery_settings() {
+        let config = Config::parse("[discovery]\nmax_depth = 4\nnested = true\n").unwrap();
+        assert_eq!(config.discovery.max_depth, 4);
+        assert!(config.discovery.nested);
+        assert!(Config::parse("[discovery]\nmax_depth = 0\n").is_err());
+    }
+
+    #[test]
+    fn watch_settings() {
+        let config = Config::parse("[watch]\nmode = \"repos\"\npoll_interval_secs = 5\n").unwrap();
+        assert_eq!(config.watch.mode, WatchMode::Repos);
+        assert_eq!(config.watch.poll_interval(), Duration::from_secs(5));
+        assert!(Config::parse("[watch]\nmode = \"inotify\"\n").is_err());
+        assert!(Config::parse("[watch]\npoll_interval_secs = 0\n").is_err());
+    }
+
+    #[test]
+    fn invalid_provider_overrides_rejected() {
+        assert!(
+            Config::parse("[clone.providers.\"a.com\"]\nurl = \"https://a.com/repo\"\n").is_err()
+        );
+        assert!(
+            Config::parse(
+                "[clone.providers.\"a.com\"]\nprotocol = \"ssh\"\nurl = \"https://a.com/{path}\"\n"
+            )
+            .is_err()
+        );
+    }
+}
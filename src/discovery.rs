@@ -0,0 +1,330 @@
+//! Finding the repos in a directory tree.
+//!
+//! Discovery walks down from a directory until it reaches a repo, skipping
+//! directories excluded by [`WorksetIgnore`] and anything deeper than
+//! `discovery.max_depth` below the workspace root.
+//!
+//! Symlinked directories are followed, but only after everything reachable
+//! without them, and every directory is entered at most once (by device and
+//! inode). So a symlink loop ends where it starts, and a repo that's also
+//! reachable through a symlink is listed once, at its real path. A symlink to
+//! a repo elsewhere is listed at the symlink's path.
+//!
+//! Normally the search stops at the first repo on each path. With
+//! `discovery.nested` it carries on inside repos to find independent repos
+//! checked out within them, leaving out their `.git` directories and
+//! submodules. The built-in ignore list doesn't apply inside repos then,
+//! since its directories are where vendored repos are usually found.
+
+use crate::config::{CONFIG_FILE, Config, DiscoveryConfig};
+use crate::ignore::WorksetIgnore;
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// What identifies a directory however it was reached
+#[cfg(unix)]
+type DirId = (u64, u64);
+#[cfg(not(unix))]
+type DirId = PathBuf;
+
+#[cfg(unix)]
+fn dir_id(path: &Path) -> Option<DirId> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_id(path: &Path) -> Option<DirId> {
+    path.canonicalize().ok()
+}
+
+/// How to search a workspace for repos
+#[derive(Debug, Clone)]
+pub struct Discovery {
+    root: PathBuf,
+    ignore: WorksetIgnore,
+    max_depth: usize,
+    nested: bool,
+}
+
+impl Discovery {
+    pub fn new(workspace_root: &Path, config: &DiscoveryConfig) -> Self {
+        let ignore = WorksetIgnore::load(workspace_root);
+        Self {
+            root: workspace_root.to_path_buf(),
+            ignore: if config.nested {
+                ignore.without_builtin_in_repos()
+            } else {
+                ignore
+            },
+            max_depth: config.max_depth,
+            nested: config.nested,
+        }
+    }
+
+    /// The settings of the workspace containing `path`, or the defaults
+    /// relative to `path` if it isn't in a workspace
+    pub fn for_path(path: &Path) -> Self {
+        match path.ancestors().find(|dir| dir.join(".workset").is_dir()) {
+            Some(root) => {
+                // The workspace already reported a broken config when it loaded
+                let config =
+                    Config::load(&root.join(".workset").join(CONFIG_FILE)).unwrap_or_default();
+                Self::new(root, &config.discovery)
+            }
+            None => Self {
+                root: path.to_path_buf(),
+                ignore: WorksetIgnore::parse(path, ""),
+                max_depth: DiscoveryConfig::default().max_depth,
+                nested: DiscoveryConfig::default().nested,
+            },
+        }
+    }
+
+    /// The independent repos inside a repo, whether or not nested discovery
+    /// is turned on. Vendored repos are what this is for, so the built-in
+    /// ignore list doesn't apply inside the repo.
+    pub fn nested_repos(&self, repo: &Path) -> Vec<PathBuf> {
+        let nested = Self {
+            root: self.root.clone(),
+            ignore: self.ignore.without_builtin_in_repos(),
+            max_depth: self.max_depth,
+            nested: true,
+        };
+        let mut repos = nested.find_repos(repo);
+        repos.retain(|found| found != repo);
+        repos
+    }
+
+    /// Find the repos at or below `path`, without looking inside them
+    pub fn find_repos(&self, path: &Path) -> Vec<PathBuf> {
+        debug!(path = %path.display(), "Recursively searching for git repositories");
+        let mut found = Vec::new();
+        let mut visited = HashSet::new();
+        let mut symlinks = VecDeque::from([path.to_path_buf()]);
+        while let Some(dir) = symlinks.pop_front() {
+            self.walk(&dir, &mut visited, &mut symlinks, &mut found);
+        }
+        found
+    }
+
+    /// Search one directory tree, queueing the symlinked directories in it
+    fn walk(
+        &self,
+        dir: &Path,
+        visited: &mut HashSet<DirId>,
+        symlinks: &mut VecDeque<PathBuf>,
+        found: &mut Vec<PathBuf>,
+    ) {
+        let Some(id) = dir_id(dir) else {
+            return;
+        };
+        if !visited.insert(id) {
+            debug!(path = %dir.display(), "Skipping directory that was already searched");
+            return;
+        }
+
+        let is_repo = dir.join(".git").exists();
+        if is_repo {
+            found.push(dir.to_path_buf());
+            if !self.nested {
+                return; // Don't traverse into git repositories
+            }
+        }
+
+        let depth = dir
+            .strip_prefix(&self.root)
+            .map_or(0, |relative| relative.components().count());
+        if depth >= self.max_depth {
+            debug!(path = %dir.display(), "Not searching below the maximum depth");
+            return;
+        }
+
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            // Permissions, or it vanished; nothing to find either way
+            return;
+        };
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by_key(|entry| entry.file_name());
+        // Submodules belong to the repo, which is listed already
+        let submodules: Vec<PathBuf> = if is_repo {
+            crate::find_submodules_in_repo(dir)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|submodule| dir.join(submodule.path))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        for entry in entries {
+            let path = entry.path();
+            if is_repo && (entry.file_name() == ".git" || submodules.contains(&path)) {
+                continue;
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let symlink = file_type.is_symlink();
+            if !(file_type.is_dir() || symlink && path.is_dir()) {
+                continue;
+            }
+            if self.ignore.is_ignored(&path) {
+                debug!(path = %path.display(), "Skipping ignored directory");
+            } else if symlink {
+                symlinks.push_back(path);
+            } else {
+                self.walk(&path, visited, symlinks, found);
+            }
+        }
+    }
+}
+
+/// The closest repo that `repo` is checked out inside of, stopping at `root`
+pub fn nesting_repo(root: &Path, repo: &Path) -> Option<PathBuf> {
+    repo.ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(root) && *dir != root)
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Whether any directory from `root` down to `path` is a symlink, meaning
+/// `path` isn't where its files really live
+pub fn through_symlink(root: &Path, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+    let mut current = root.to_path_buf();
+    relative.components().any(|component| {
+        current.push(component);
+        current.is_symlink()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn discovery(root: &Path, max_depth: usize) -> Discovery {
+        Discovery::new(
+            root,
+            &DiscoveryConfig {
+                max_depth,
+                nested: false,
+            },
+        )
+    }
+
+    #[test]
+    fn nested_repos_found_on_request() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("app/.git/objects")).unwrap();
+        fs::create_dir_all(root.join("app/deps/lib/.git")).unwrap();
+        fs::create_dir_all(root.join("app/vendor/tool/.git")).unwrap();
+        fs::create_dir_all(root.join("app/sub/.git")).unwrap();
+        fs::write(
+            root.join("app/.gitmodules"),
+            "[submodule \"sub\"]\n\tpath = sub\n\turl = https://example.com/sub.git\n",
+        )
+        .unwrap();
+
+        let flat = discovery(root, 10);
+        assert_eq!(flat.find_repos(root), vec![root.join("app")]);
+        assert_eq!(
+            flat.nested_repos(&root.join("app")),
+            vec![root.join("app/deps/lib"), root.join("app/vendor/tool")]
+        );
+
+        let nested = Discovery::new(
+            root,
+            &DiscoveryConfig {
+                max_depth: 10,
+                nested: true,
+            },
+        );
+        assert_eq!(
+            nested.find_repos(root),
+            vec![
+                root.join("app"),
+                root.join("app/deps/lib"),
+                root.join("app/vendor/tool")
+            ]
+        );
+        assert_eq!(
+            nesting_repo(root, &root.join("app/deps/lib")),
+            Some(root.join("app"))
+        );
+        assert_eq!(nesting_repo(root, &root.join("app")), None);
+    }
+
+    #[test]
+    fn builtin_ignores_prune_outside_repos() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("github.com/me/app/.git")).unwrap();
+        for i in 0..200 {
+            fs::create_dir_all(root.join(format!("scratch/node_modules/pkg{i}/lib/util"))).unwrap();
+        }
+        // Too deep in the dependency tree to be looked for
+        fs::create_dir_all(root.join("scratch/node_modules/pkg0/lib/util/.git")).unwrap();
+
+        for nested in [false, true] {
+            let discovery = Discovery::new(
+                root,
+                &DiscoveryConfig {
+                    max_depth: 10,
+                    nested,
+                },
+            );
+            assert_eq!(
+                discovery.find_repos(root),
+                vec![root.join("github.com/me/app")]
+            );
+        }
+    }
+
+    #[test]
+    fn max_depth_limits_search() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("a/.git")).unwrap();
+        fs::create_dir_all(root.join("b/c/.git")).unwrap();
+        fs::create_dir_all(root.join("d/e/f/.git")).unwrap();
+
+        let found = discovery(root, 2).find_repos(root);
+        assert_eq!(found, vec![root.join("a"), root.join("b/c")]);
+        // Depth counts from the workspace root, wherever the search starts
+        assert_eq!(
+            discovery(root, 2).find_repos(&root.join("d")),
+            Vec::<PathBuf>::new()
+        );
+        assert_eq!(
+            discovery(root, 3).find_repos(&root.join("d")),
+            vec![root.join("d/e/f")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_followed_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("ws");
+        let outside = temp_dir.path().join("outside");
+        fs::create_dir_all(root.join("real/repo/.git")).unwrap();
+        fs::create_dir_all(outside.join(".git")).unwrap();
+        // A loop back to the root, an alias of a repo, and a repo elsewhere
+        std::os::unix::fs::symlink(&root, root.join("real/loop")).unwrap();
+        std::os::unix::fs::symlink(root.join("real/repo"), root.join("alias")).unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("linked")).unwrap();
+
+        let found = discovery(&root, 10).find_repos(&root);
+        assert_eq!(found, vec![root.join("real/repo"), root.join("linked")]);
+        assert!(through_symlink(&root, &root.join("linked")));
+        assert!(!through_symlink(&root, &root.join("real/repo")));
+    }
+}
@@ -0,0 +1,129 @@
+//! Errors that callers are expected to tell apart.
+//!
+//! Most failures are plain [`anyhow`] errors with context attached. The ones
+//! here are raised on purpose so the CLI can exit with a distinct code and
+//! scripts can react: find them with `anyhow::Error::downcast_ref::<Error>()`.
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The command line was wrong: a missing argument, an unknown command
+    Usage(String),
+    /// The command needs a workspace and none was found
+    NotInWorkspace,
+    /// A pattern that should name one repo matched several
+    AmbiguousPattern {
+        pattern: String,
+        candidates: Vec<String>,
+    },
+    /// Repos were left alone to protect work that would otherwise be lost
+    Refused { action: String, repos: Vec<Refusal> },
+    /// Some of a command's items failed while the rest were carried out
+    PartialFailure { failed: usize, total: usize },
+}
+
+/// Why one repo was left alone
+#[derive(Debug, Clone)]
+pub struct Refusal {
+    /// Path relative to the workspace root
+    pub repo: String,
+    /// What stopped it, phrased to follow the repo's path
+    pub reason: String,
+    /// Whether `--force` would have gone ahead anyway
+    pub forceable: bool,
+}
+
+impl fmt::Display for Refusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.repo, self.reason)?;
+        if self.forceable {
+            write!(f, " (use --force to override)")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error {
+    /// Process exit code for the CLI; other errors exit with 1
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Usage(_) | Error::NotInWorkspace | Error::AmbiguousPattern { .. } => 2,
+            Error::Refused { .. } => 3,
+            Error::PartialFailure { .. } => 4,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(message) => write!(f, "{}", message),
+            Error::NotInWorkspace => write!(f, "Not in a workspace (run 'workset init' first)"),
+            Error::AmbiguousPattern {
+                pattern,
+                candidates,
+            } => write!(
+                f,
+                "'{}' matches {} repositories:\n  {}\nUse a longer path to pick one, or a glob to select several",
+                pattern,
+                candidates.len(),
+                candidates.join("\n  ")
+            ),
+            Error::Refused { action, repos } => {
+                write!(f, "Refused to {} {} repository(ies):", action, repos.len())?;
+                for refusal in repos {
+                    write!(f, "\n  {}", refusal)?;
+                }
+                Ok(())
+            }
+            Error::PartialFailure { failed, total } => {
+                write!(f, "{} of {} failed", failed, total)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes_survive_anyhow() {
+        let err: anyhow::Error = Error::PartialFailure {
+            failed: 1,
+            total: 3,
+        }
+        .into();
+        let err = err.context("restoring");
+        assert_eq!(err.downcast_ref::<Error>().map(Error::exit_code), Some(4));
+        assert_eq!(Error::NotInWorkspace.exit_code(), 2);
+    }
+
+    #[test]
+    fn refusals_say_why() {
+        let err = Error::Refused {
+            action: "drop".to_string(),
+            repos: vec![
+                Refusal {
+                    repo: "app".to_string(),
+                    reason: "has uncommitted changes".to_string(),
+                    forceable: true,
+                },
+                Refusal {
+                    repo: "linked".to_string(),
+                    reason: "is reached through a symlink; remove the link instead".to_string(),
+                    forceable: false,
+                },
+            ],
+        };
+        assert_eq!(
+            err.to_string(),
+            "Refused to drop 2 repository(ies):\n  \
+             app has uncommitted changes (use --force to override)\n  \
+             linked is reached through a symlink; remove the link instead"
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Directories left out of repo discovery and file watching.
+//!
+//! Walking into build output, dependency trees and mounted volumes makes
+//! scans slow and costs the TUI's watcher a watch per directory, without ever
+//! turning up a repo worth listing. A `.worksetignore` file at the workspace
+//! root lists more directories to skip, in gitignore syntax: a pattern
+//! without a slash matches a directory of that name anywhere, one with a
+//! slash is relative to the workspace root, `*`, `?` and `**` are globs, and
+//! `!` re-includes what an earlier pattern excluded.
+//!
+//! A built-in list of common offenders comes first, so `!target` in the file
+//! brings back directories called `target`. A directory that is a repo
+//! itself is never hidden by them. Outside of repos, neither is one with a
+//! repo up to two levels below it, like an organization or group called
+//! `vendor` on the way to repos; looking any deeper would mean walking the
+//! dependency trees the list is there to skip.
+
+use crate::match_segments;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Name of the ignore file at the root of the workspace
+pub const IGNORE_FILE: &str = ".worksetignore";
+
+/// Directories skipped unless the ignore file says otherwise
+pub const DEFAULT_IGNORES: &[&str] = &[
+    ".workset",
+    "node_modules",
+    "bower_components",
+    "target",
+    "vendor",
+    ".venv",
+    "venv",
+    "__pycache__",
+    ".tox",
+    ".gradle",
+    ".terraform",
+    ".direnv",
+    ".cache",
+];
+
+/// How many levels below a directory matched by a built-in pattern outside
+/// of repos to look for a repo before hiding it
+const REPO_PROBE_DEPTH: usize = 2;
+
+/// One line of an ignore file
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Path segments relative to the workspace root; unanchored patterns
+    /// start with `**`
+    segments: Vec<String>,
+    negated: bool,
+    builtin: bool,
+}
+
+impl Rule {
+    fn parse(line: &str, builtin: bool) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        // Only directories are ever matched, so a trailing slash changes nothing
+        let pattern = pattern.trim_end_matches('/');
+        let anchored = pattern.contains('/');
+        let mut segments: Vec<String> = pattern
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+        if segments.is_empty() {
+            return None;
+        }
+        if !anchored {
+            segments.insert(0, "**".to_string());
+        }
+        Some(Self {
+            segments,
+            negated,
+            builtin,
+        })
+    }
+
+    fn matches(&self, path: &[&str]) -> bool {
+        let segments: Vec<&str> = self.segments.iter().map(String::as_str).collect();
+        match_segments(&segments, path)
+    }
+}
+
+/// The ignore rules of a workspace
+#[derive(Debug, Clone)]
+pub struct WorksetIgnore {
+    root: PathBuf,
+    rules: Vec<Rule>,
+    /// Whether the built-in rules hide directories inside repos
+    builtin_in_repos: bool,
+}
+
+impl WorksetIgnore {
+    /// The built-in rules followed by those from the workspace's ignore file
+    pub fn load(workspace_root: &Path) -> Self {
+        let content = match std::fs::read_to_string(workspace_root.join(IGNORE_FILE)) {
+            Ok(content) => content,
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    debug!(error = %e, "Failed to read {}", IGNORE_FILE);
+                }
+                String::new()
+            }
+        };
+        Self::parse(workspace_root, &content)
+    }
+
+    /// The rules of the workspace containing `path`, or only the built-in
+    /// ones relative to `path` if it isn't in a workspace
+    pub fn for_path(path: &Path) -> Self {
+        match path.ancestors().find(|dir| dir.join(".workset").is_dir()) {
+            Some(root) => Self::load(root),
+            None => Self::parse(path, ""),
+        }
+    }
+
+    /// The built-in rules followed by those in `content`
+    pub fn parse(workspace_root: &Path, content: &str) -> Self {
+        let builtin = DEFAULT_IGNORES
+            .iter()
+            .filter_map(|pattern| Rule::parse(pattern, true));
+        let custom = content.lines().filter_map(|line| Rule::parse(line, false));
+        Self {
+            root: workspace_root.to_path_buf(),
+            rules: builtin.chain(custom).collect(),
+            builtin_in_repos: true,
+        }
+    }
+
+    /// Only the rules from the ignore file inside repos, for searches that
+    /// must not miss anything in a repo's build and dependency directories
+    pub fn without_builtin_in_repos(&self) -> Self {
+        Self {
+            builtin_in_repos: false,
+            ..self.clone()
+        }
+    }
+
+    /// Whether discovery and the watcher should stay out of a directory.
+    /// Like in gitignore, the last matching pattern decides.
+    pub fn is_ignored(&self, dir: &Path) -> bool {
+        let Ok(relative) = dir.strip_prefix(&self.root) else {
+            return false;
+        };
+        let relative = relative.to_string_lossy();
+        let segments: Vec<&str> = relative.split('/').filter(|s| !s.is_empty()).collect();
+        if segments.is_empty() {
+            return false;
+        }
+        match self.rules.iter().rev().find(|rule| rule.matches(&segments)) {
+            Some(rule) if rule.negated => false,
+            Some(rule) if rule.builtin => {
+                if self.inside_repo(dir) {
+                    self.builtin_in_repos
+                } else {
+                    !holds_repo(dir, REPO_PROBE_DEPTH)
+                }
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    /// Whether a directory is somewhere inside a repo, without being one
+    fn inside_repo(&self, dir: &Path) -> bool {
+        !dir.join(".git").exists()
+            && dir
+                .ancestors()
+                .skip(1)
+                .take_while(|ancestor| ancestor.starts_with(&self.root) && *ancestor != self.root)
+                .any(|ancestor| ancestor.join(".git").exists())
+    }
+}
+
+/// Whether a directory is a repo or has one up to `depth` levels below it
+fn holds_repo(dir: &Path, depth: usize) -> bool {
+    if dir.join(".git").exists() {
+        return true;
+    }
+    if depth == 0 {
+        return false;
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return false;
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .any(|path| path.is_dir() && holds_repo(&path, depth - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn patterns_follow_gitignore_rules() {
+        let root = Path::new("/ws");
+        let ignore = WorksetIgnore::parse(
+            root,
+            "# comment\n\nscratch/\n/archive\ndocs/*/build\n*.tmp\n!target\n",
+        );
+
+        // Unanchored patterns match at any depth, anchored ones at the root
+        assert!(ignore.is_ignored(&root.join("scratch")));
+        assert!(ignore.is_ignored(&root.join("a/b/scratch")));
+        assert!(ignore.is_ignored(&root.join("archive")));
+        assert!(!ignore.is_ignored(&root.join("old/archive")));
+        assert!(ignore.is_ignored(&root.join("docs/site/build")));
+        assert!(!ignore.is_ignored(&root.join("docs/site/v1/build")));
+        assert!(ignore.is_ignored(&root.join("x/cache.tmp")));
+        // The root itself and paths outside it are never ignored
+        assert!(!ignore.is_ignored(root));
+        assert!(!ignore.is_ignored(Path::new("/elsewhere/scratch")));
+    }
+
+    #[test]
+    fn builtin_patterns_never_hide_repos() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("github.com/me/app/.git")).unwrap();
+        std::fs::create_dir_all(root.join("github.com/me/app/node_modules")).unwrap();
+        std::fs::create_dir_all(root.join("github.com/me/app/target")).unwrap();
+        std::fs::create_dir_all(root.join("github.com/me/target/.git")).unwrap();
+        std::fs::create_dir_all(root.join("github.com/vendor/x/.git")).unwrap();
+        std::fs::create_dir_all(root.join("gitlab.com/group/.cache/y/.git")).unwrap();
+        std::fs::create_dir_all(root.join("mine/.git")).unwrap();
+
+        let ignore = WorksetIgnore::parse(root, "mine\n!target\n");
+        // Build output and dependencies inside a repo, with `target` brought back
+        assert!(ignore.is_ignored(&root.join("github.com/me/app/node_modules")));
+        assert!(!ignore.is_ignored(&root.join("github.com/me/app/target")));
+        // Repos, and directories on the way to them, are never hidden
+        assert!(!ignore.is_ignored(&root.join("github.com/me/target")));
+        assert!(!ignore.is_ignored(&root.join("github.com/vendor")));
+        assert!(!ignore.is_ignored(&root.join("gitlab.com/group/.cache")));
+        // Unless the ignore file says so
+        assert!(ignore.is_ignored(&root.join("mine")));
+
+        let found = crate::discovery::Discovery::new(root, &Default::default()).find_repos(root);
+        assert!(found.contains(&root.join("github.com/vendor/x")));
+        assert!(found.contains(&root.join("gitlab.com/group/.cache/y")));
+    }
+}
@@ -1,15 +1,33 @@
 use anyhow::{Result, bail};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::Path;
 use std::path::PathBuf;
 use std::str::FromStr;
-use tracing::{debug, info, warn};
-
+use tracing::{debug, error, info, warn};
+
+pub use error::{Error, Refusal};
+pub use state::RepoState;
+
+pub mod cache;
+pub mod config;
+pub mod discovery;
+pub mod error;
+pub mod ignore;
+pub mod library;
+pub mod manifest;
+pub mod prune;
+pub mod scan;
+pub mod state;
 pub mod sync;
+#[cfg(test)]
+mod test_util;
 #[cfg(feature = "tui")]
 pub mod tui;
+pub mod usage;
 
 /// Represents a pattern that matches one or more repositories. It has the
-/// format: [provider]/<path>.
+/// format: [provider]/<path>, or is a git URL that gets normalized into that
+/// form.
 #[derive(Debug, Eq, PartialEq)]
 pub struct RepoPattern {
     /// The provider (e.g., "github.com", "gitlab.com")
@@ -17,26 +35,77 @@ pub struct RepoPattern {
 
     /// The repo path
     pub path: String,
+
+    /// The URL the pattern was parsed from, if it was one
+    pub url: Option<String>,
 }
 
 impl FromStr for RepoPattern {
     type Err = std::convert::Infallible;
 
     fn from_str(path: &str) -> std::result::Result<Self, Self::Err> {
+        if let Some((host, repo_path)) = parse_git_url(path) {
+            return Ok(Self {
+                provider: Some(host.to_string()),
+                path: repo_path.to_string(),
+                url: Some(path.to_string()),
+            });
+        }
+
         // If the first component looks like a domain (contains '.'), it's a provider
         Ok(match path.split_once('/') {
-            Some((first, rest)) if first.contains('.') => Self {
-                provider: Some(first.to_string()),
-                path: rest.to_string(),
-            },
+            Some((first, rest)) if first.contains('.') && !first.trim_matches('.').is_empty() => {
+                Self {
+                    provider: Some(first.to_string()),
+                    path: rest.to_string(),
+                    url: None,
+                }
+            }
             _ => Self {
                 provider: None,
                 path: path.to_string(),
+                url: None,
             },
         })
     }
 }
 
+/// Split a git URL into its host and repo path, dropping any user, port, and
+/// `.git` suffix. Understands `scheme://[user@]host[:port]/path` and
+/// scp-style `[user@]host:path`.
+fn parse_git_url(url: &str) -> Option<(&str, &str)> {
+    let (authority, path) = if let Some((scheme, rest)) = url.split_once("://") {
+        if !matches!(
+            scheme,
+            "http" | "https" | "ssh" | "git" | "git+ssh" | "ssh+git"
+        ) {
+            return None;
+        }
+        rest.split_once('/')?
+    } else {
+        // scp-style syntax has a ':' before any '/'
+        let (authority, path) = url.split_once(':')?;
+        if authority.contains('/') || !(authority.contains('@') || authority.contains('.')) {
+            return None;
+        }
+        (authority, path)
+    };
+
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+        _ => host,
+    };
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    if host.is_empty() || path.is_empty() {
+        return None;
+    }
+    Some((host, path))
+}
+
 impl RepoPattern {
     /// Get the provider and path as a tuple if provider exists
     pub fn provider_and_path(&self) -> Option<(&str, &str)> {
@@ -52,6 +121,125 @@ impl RepoPattern {
             None => self.path.clone(),
         }
     }
+
+    /// Whether the pattern is a path relative to the current directory
+    /// (`./repo`, `../other`) rather than a pattern over workspace paths
+    pub fn is_relative_path(&self) -> bool {
+        self.provider.is_none()
+            && (self.path == "."
+                || self.path == ".."
+                || self.path.starts_with("./")
+                || self.path.starts_with("../"))
+    }
+
+    /// Whether the pattern contains globs, meaning it's expected to match
+    /// more than one repo
+    pub fn is_glob(&self) -> bool {
+        self.full_path().contains(['*', '?'])
+    }
+
+    /// Whether the pattern matches a repo's workspace- or library-relative
+    /// path. The pattern's segments must equal whole trailing segments of the
+    /// path, so `jq` matches `github.com/jqlang/jq` but not `jquery`. Within a
+    /// segment `*` matches any run of characters and `?` any one character;
+    /// a `**` segment matches any number of segments.
+    pub fn matches(&self, repo_path: &str) -> bool {
+        let full_path = self.full_path();
+        let pattern: Vec<&str> = full_path.split('/').filter(|s| !s.is_empty()).collect();
+        let path: Vec<&str> = repo_path.split('/').filter(|s| !s.is_empty()).collect();
+        (0..path.len()).any(|start| match_segments(&pattern, &path[start..]))
+    }
+
+    /// Looser matching for when the exact name isn't known: the pattern's
+    /// characters must appear in order in the path, ignoring case
+    pub fn fuzzy_matches(&self, repo_path: &str) -> bool {
+        let mut path = repo_path.chars().flat_map(char::to_lowercase);
+        self.full_path()
+            .chars()
+            .flat_map(char::to_lowercase)
+            .all(|c| path.any(|p| p == c))
+    }
+
+    /// Select the candidate paths matched by the pattern. A pattern without
+    /// globs names a single repo, so if it matches several (and none of them
+    /// exactly) the candidates are listed in an error instead.
+    pub fn select<S: AsRef<str> + Clone>(&self, candidates: &[S], fuzzy: bool) -> Result<Vec<S>> {
+        let matched: Vec<S> = candidates
+            .iter()
+            .filter(|c| {
+                if fuzzy {
+                    self.fuzzy_matches(c.as_ref())
+                } else {
+                    self.matches(c.as_ref())
+                }
+            })
+            .cloned()
+            .collect();
+        if matched.len() <= 1 || self.is_glob() {
+            return Ok(matched);
+        }
+
+        let full_path = self.full_path();
+        if let Some(exact) = matched.iter().find(|c| c.as_ref() == full_path) {
+            return Ok(vec![exact.clone()]);
+        }
+        Err(Error::AmbiguousPattern {
+            pattern: full_path,
+            candidates: matched.iter().map(|c| c.as_ref().to_string()).collect(),
+        }
+        .into())
+    }
+
+    /// The URL to clone the repo from: the URL the pattern was parsed from,
+    /// or one built from the provider and path according to the config
+    pub fn clone_url(&self, config: &config::Config) -> Option<String> {
+        if let Some(url) = &self.url {
+            return Some(url.clone());
+        }
+        self.provider_and_path()
+            .map(|(provider, path)| config.clone_url(provider, path))
+    }
+}
+
+/// Match pattern segments against path segments, both anchored at the ends
+pub(crate) fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_segments(rest, &path[skip..])),
+        Some((first, rest)) => path.split_first().is_some_and(|(segment, path)| {
+            glob_segment(first, segment) && match_segments(rest, path)
+        }),
+    }
+}
+
+/// Match a single path segment against a glob with `*` and `?`
+fn glob_segment(pattern: &str, segment: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let segment: Vec<char> = segment.chars().collect();
+    let (mut p, mut s) = (0, 0);
+    // Where to resume after the last `*` if the current attempt fails
+    let mut backtrack = None;
+    while s < segment.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, s));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == segment[s] => {
+                p += 1;
+                s += 1;
+            }
+            _ => match backtrack {
+                Some((star, star_s)) => {
+                    p = star + 1;
+                    s = star_s + 1;
+                    backtrack = Some((star, star_s + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
 /// Represents a git submodule within a repository
@@ -68,36 +256,114 @@ pub struct SubmoduleInfo {
 }
 
 /// Recursively find "top-level" git repositories.
-/// This function will not traverse into .git directories or nested git repositories.
+/// This function will not traverse into .git directories, nested git
+/// repositories, or directories excluded by the workspace's `.worksetignore`,
+/// and follows symlinks without listing a repo twice (see [`discovery`]).
 pub fn find_git_repositories(path: &Path) -> Result<Vec<PathBuf>> {
-    debug!(path = %path.display(), "Recursively searching for git repositories");
-    let mut found: Vec<PathBuf> = Vec::new();
-
-    // Check if this path itself is a git repository
-    if path.join(".git").exists() {
-        found.push(path.to_path_buf());
-        return Ok(found); // Don't traverse into git repositories
-    }
-
-    // Otherwise, recursively search subdirectories
-    if let Ok(entries) = std::fs::read_dir(path) {
-        for entry in entries.filter_map(|e| e.ok()) {
-            let entry_path = entry.path();
-
-            // Only traverse directories
-            if entry_path.is_dir() {
-                match find_git_repositories(&entry_path) {
-                    Ok(mut repos) => found.append(&mut repos),
-                    Err(e) => {
-                        // Log but don't fail on permission errors
-                        debug!(path = %entry_path.display(), error = %e, "Skipping directory");
-                    }
-                }
+    Ok(discovery::Discovery::for_path(path).find_repos(path))
+}
+
+/// Where a repo keeps its git data. `.git` is usually that directory, but it
+/// can also be a file holding `gitdir: <path>`, as in linked worktrees
+/// (`git worktree add`) and repos made with `git init --separate-git-dir`.
+pub fn git_dir(repo: &Path) -> PathBuf {
+    let dot_git = repo.join(".git");
+    if dot_git.is_file()
+        && let Ok(content) = std::fs::read_to_string(&dot_git)
+        && let Some(target) = content
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("gitdir:"))
+    {
+        return normalize_path(&repo.join(target.trim()));
+    }
+    dot_git
+}
+
+/// The git directory shared by all of a repo's worktrees, which holds its
+/// objects, refs and config
+pub fn common_git_dir(repo: &Path) -> PathBuf {
+    let git_dir = git_dir(repo);
+    match std::fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common) => normalize_path(&git_dir.join(common.trim())),
+        Err(_) => git_dir,
+    }
+}
+
+/// If the repo is a linked worktree, the root of the main worktree it was
+/// added from (or the main repo's git directory, if that's bare)
+pub fn worktree_main(repo: &Path) -> Option<PathBuf> {
+    if !repo.join(".git").is_file() {
+        return None;
+    }
+    let git_dir = git_dir(repo);
+    let common = std::fs::read_to_string(git_dir.join("commondir")).ok()?;
+    let common = normalize_path(&git_dir.join(common.trim()));
+    if common.file_name().is_some_and(|name| name == ".git") {
+        common.parent().map(Path::to_path_buf)
+    } else {
+        Some(common)
+    }
+}
+
+/// The linked worktrees added from a repo that still exist on disk
+pub fn linked_worktrees(repo: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(common_git_dir(repo).join("worktrees")) else {
+        return Vec::new();
+    };
+    let mut worktrees: Vec<PathBuf> = entries
+        .flatten()
+        .filter_map(|entry| std::fs::read_to_string(entry.path().join("gitdir")).ok())
+        .filter_map(|gitdir| {
+            // Points at the worktree's .git file
+            Path::new(gitdir.trim()).parent().map(Path::to_path_buf)
+        })
+        .filter(|worktree| worktree.join(".git").is_file())
+        .collect();
+    worktrees.sort();
+    worktrees
+}
+
+/// Resolve `.` and `..` components without touching the filesystem, so paths
+/// keep the workspace prefix they were built from even through symlinks
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
             }
+            component => normalized.push(component),
         }
     }
+    normalized
+}
 
-    Ok(found)
+/// The initialized submodules of a repo, and of those in turn, that have
+/// uncommitted changes or commits no remote has. Their work lives only in
+/// their own git directories, which a check of the parent doesn't look into.
+pub fn unsaved_submodules(repo_path: &Path) -> Vec<(PathBuf, RepoStatus)> {
+    let mut unsaved = Vec::new();
+    for submodule in find_submodules_in_repo(repo_path).unwrap_or_default() {
+        if !submodule.initialized {
+            continue;
+        }
+        let path = repo_path.join(&submodule.path);
+        match check_repo_status(&path) {
+            Ok(status @ (RepoStatus::Dirty | RepoStatus::Unpushed)) => {
+                unsaved.push((submodule.path.clone(), status));
+            }
+            Ok(_) => {}
+            Err(e) => debug!(submodule = %path.display(), error = %e, "Failed to check submodule"),
+        }
+        unsaved.extend(
+            unsaved_submodules(&path)
+                .into_iter()
+                .map(|(nested, status)| (submodule.path.join(nested), status)),
+        );
+    }
+    unsaved
 }
 
 /// Find all submodules in a git repository by parsing the .gitmodules file
@@ -202,8 +468,112 @@ pub fn gix_clone(url: &str, dest: &Path) -> Result<gix::Repository> {
     Ok(repo)
 }
 
+/// Set `core.bare` in a repository's config file
+fn set_core_bare(git_dir: &Path, bare: bool) -> Result<()> {
+    let config_path = git_dir.join("config");
+    let config_content = std::fs::read_to_string(&config_path)?;
+    let line = format!("\tbare = {}", bare);
+
+    let new_config = if config_content.contains("[core]") {
+        // Replace or add the bare setting under [core]
+        if config_content.contains("bare =") || config_content.contains("bare=") {
+            config_content
+                .lines()
+                .map(|l| {
+                    if l.trim().starts_with("bare") {
+                        line.clone()
+                    } else {
+                        l.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+                + "\n"
+        } else {
+            config_content.replacen("[core]", &format!("[core]\n{}", line), 1)
+        }
+    } else {
+        format!("{}\n[core]\n{}\n", config_content, line)
+    };
+
+    std::fs::write(&config_path, new_config)?;
+    Ok(())
+}
+
+/// Turn a bare repo that was moved into `repo/.git` back into a checkout,
+/// writing the worktree from the index it was dropped with
+fn checkout_from_index(repo: &Path) -> Result<()> {
+    set_core_bare(&repo.join(".git"), false)?;
+    // A repo dropped before its first commit has nothing to check out
+    if !repo.join(".git/index").exists() {
+        return Ok(());
+    }
+    git(repo, &["checkout-index", "--all", "--force"])?;
+    git(repo, &["update-index", "-q", "--refresh"])?;
+    Ok(())
+}
+
+/// Ref inside a repo's .git that holds work shelved by `drop --shelve`
+pub const SHELF_REF: &str = "refs/workset/shelf";
+
+/// Whether the repo has shelved work waiting to be reapplied
+pub fn has_shelf(repo: &Path) -> bool {
+    git(repo, &["rev-parse", "--verify", "--quiet", SHELF_REF]).is_ok()
+}
+
+/// Snapshot the index, worktree changes and untracked files into
+/// [`SHELF_REF`], leaving the worktree clean. The user's own stash list is
+/// left as it was.
+fn shelve_changes(repo: &Path) -> Result<()> {
+    if has_shelf(repo) {
+        bail!(
+            "{} already holds shelved work that was never reapplied",
+            SHELF_REF
+        );
+    }
+    git(
+        repo,
+        &[
+            "stash",
+            "push",
+            "--include-untracked",
+            "--quiet",
+            "--message",
+            "shelved by workset drop",
+        ],
+    )?;
+    git(repo, &["update-ref", SHELF_REF, "stash@{0}"])?;
+    git(repo, &["stash", "drop", "--quiet"])?;
+    Ok(())
+}
+
+/// Reapply work shelved by [`shelve_changes`], staged changes included, and
+/// remove the shelf. If it doesn't apply cleanly the shelf is kept.
+fn unshelve_changes(repo: &Path) -> Result<()> {
+    git(repo, &["stash", "apply", "--index", "--quiet", SHELF_REF])?;
+    git(repo, &["update-ref", "-d", SHELF_REF])?;
+    Ok(())
+}
+
+/// Run a local git command in a repo and return its stdout
+fn git(repo: &Path, args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(repo)
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "git {}: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
 /// Repository status information
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum RepoStatus {
     /// Repository is clean (has commits, no changes, no unpushed)
     Clean,
@@ -215,20 +585,25 @@ pub enum RepoStatus {
     Unpushed,
 }
 
+/// Remote names and fetch URLs of the repo at the given path
+pub fn remote_urls(repo_path: &Path) -> BTreeMap<String, String> {
+    let mut remotes = BTreeMap::new();
+    let Ok(repo) = gix::open(repo_path) else {
+        return remotes;
+    };
+    for name in repo.remote_names() {
+        if let Ok(remote) = repo.find_remote(name.as_ref())
+            && let Some(url) = remote.url(gix::remote::Direction::Fetch)
+        {
+            remotes.insert(name.to_string(), url.to_bstring().to_string());
+        }
+    }
+    remotes
+}
+
 /// Check repository status (commits, changes, unpushed) in a single pass
 pub fn check_repo_status(repo_path: &Path) -> Result<RepoStatus> {
-    let repo = match gix::open(repo_path) {
-        Ok(r) => r,
-        Err(e) => {
-            warn!(
-                path = %repo_path.display(),
-                error = %e,
-                "Failed to open repository"
-            );
-            return Ok(RepoStatus::NoCommits);
-        }
-    };
-    check_repo_status_with_handle(&repo, repo_path)
+    Ok(check_repo_status_and_modification_time(repo_path)?.0)
 }
 
 /// Check repository status and get modification time in a single repo open
@@ -236,6 +611,18 @@ pub fn check_repo_status(repo_path: &Path) -> Result<RepoStatus> {
 pub fn check_repo_status_and_modification_time(
     repo_path: &Path,
 ) -> Result<(RepoStatus, Option<std::time::SystemTime>)> {
+    Ok(match check_repo_state_and_modification_time(repo_path)? {
+        (Some(state), modified) => (state.status(), modified),
+        (None, modified) => (RepoStatus::NoCommits, modified),
+    })
+}
+
+/// Read a repository's full state along with its modification time: the
+/// last commit time, or the latest change to a dirty file if that's newer.
+/// The state is None if the repo can't be opened.
+pub fn check_repo_state_and_modification_time(
+    repo_path: &Path,
+) -> Result<(Option<RepoState>, Option<std::time::SystemTime>)> {
     let repo = match gix::open(repo_path) {
         Ok(r) => r,
         Err(e) => {
@@ -244,122 +631,242 @@ pub fn check_repo_status_and_modification_time(
                 error = %e,
                 "Failed to open repository"
             );
-            return Ok((RepoStatus::NoCommits, None));
+            return Ok((None, None));
         }
     };
 
-    let (has_changes, dirty_files_time) = scan_worktree_changes(&repo, repo_path);
-
-    let Some(head_ref) = head_referent(&repo) else {
-        return Ok((RepoStatus::NoCommits, Some(dirty_files_time)));
-    };
-
-    if has_changes {
+    let (state, dirty_files_time) = state::scan(&repo, repo_path);
+    let modified = match state.status() {
+        RepoStatus::NoCommits => Some(dirty_files_time),
         // For dirty repos, use the max of last commit time and dirty file times
-        let commit_time = get_last_commit_time(&repo).unwrap_or(std::time::SystemTime::UNIX_EPOCH);
-        return Ok((RepoStatus::Dirty, Some(commit_time.max(dirty_files_time))));
-    }
-
-    let status = check_unpushed_status(&repo, head_ref);
-    Ok((status, get_last_commit_time(&repo).ok()))
+        RepoStatus::Dirty => {
+            let commit_time =
+                get_last_commit_time(&repo).unwrap_or(std::time::SystemTime::UNIX_EPOCH);
+            Some(commit_time.max(dirty_files_time))
+        }
+        _ => get_last_commit_time(&repo).ok(),
+    };
+    Ok((Some(state), modified))
 }
 
-/// Get the HEAD reference, or None if the repository has no commits
-fn head_referent(repo: &gix::Repository) -> Option<gix::Reference<'_>> {
-    repo.head().ok()?.try_into_referent()
+/// Local branches and tags (and a detached HEAD) with commits that no
+/// remote-tracking ref contains, by short name. A repo without remotes has
+/// nowhere its work is kept, so everything in it counts as unpushed. Tags
+/// known to be on a remote are taken as pushed along with their history,
+/// even when no remote branch contains them.
+pub fn unpushed_refs(repo_path: &Path) -> Result<Vec<String>> {
+    let repo = gix::open(repo_path)?;
+    Ok(unpushed_refs_with_handle(&repo))
 }
 
-/// Check repository status using an already-opened repository handle.
-/// Stops scanning the worktree at the first change found.
-fn check_repo_status_with_handle(repo: &gix::Repository, repo_path: &Path) -> Result<RepoStatus> {
-    let Some(head_ref) = head_referent(repo) else {
-        return Ok(RepoStatus::NoCommits);
+pub(crate) fn unpushed_refs_with_handle(repo: &gix::Repository) -> Vec<String> {
+    let Ok(platform) = repo.references() else {
+        return Vec::new();
     };
-
-    // Check for uncommitted changes using a single status call
-    let platform = match repo.status(gix::progress::Discard) {
-        Ok(p) => p,
-        Err(e) => {
-            warn!(
-                path = %repo_path.display(),
-                error = %e,
-                "Failed to create status platform"
-            );
-            return Ok(RepoStatus::Clean);
-        }
+    // Tags can point at trees or blobs, which have no history to check
+    let peeled = |mut reference: gix::Reference<'_>| {
+        let name = reference.name().shorten().to_string();
+        let id = reference.peel_to_commit().ok()?.id;
+        Some((name, id))
     };
 
-    // Check both tracked changes and untracked files in one pass
-    let has_changes = match platform
-        .untracked_files(gix::status::UntrackedFiles::Files)
-        .into_index_worktree_iter(Vec::new())
-    {
-        Ok(mut iter) => iter.by_ref().flatten().next().is_some(),
+    let mut remote_tips: Vec<gix::ObjectId> = match platform.remote_branches() {
+        Ok(iter) => iter
+            .flatten()
+            .filter_map(peeled)
+            .map(|(_, id)| id)
+            .collect(),
         Err(e) => {
-            warn!(
-                path = %repo_path.display(),
-                error = %e,
-                "Failed to check for changes"
-            );
-            false
+            debug!(error = %e, "Failed to list remote-tracking refs");
+            Vec::new()
         }
     };
-
-    if has_changes {
-        return Ok(RepoStatus::Dirty);
+    remote_tips.extend(remote_tags(repo));
+    let mut local: Vec<(String, gix::ObjectId)> = Vec::new();
+    for iter in [platform.local_branches(), platform.tags()] {
+        match iter {
+            Ok(iter) => local.extend(iter.flatten().filter_map(peeled)),
+            Err(e) => debug!(error = %e, "Failed to list local refs"),
+        }
+    }
+    if let Ok(head) = repo.head()
+        && head.is_detached()
+        && let Some(id) = head.id()
+    {
+        local.push(("HEAD".to_string(), id.detach()));
     }
 
-    Ok(check_unpushed_status(repo, head_ref))
+    let tips: Vec<gix::ObjectId> = local.iter().map(|(_, id)| *id).collect();
+    let pushed = reachable_from(repo, &tips, &remote_tips);
+    local
+        .into_iter()
+        .filter(|(_, id)| !pushed.contains(id))
+        .map(|(name, _)| name)
+        .collect()
 }
 
-/// Classify a repository with no uncommitted changes as Clean or Unpushed
-fn check_unpushed_status(repo: &gix::Repository, head_ref: gix::Reference<'_>) -> RepoStatus {
-    let local_branch = head_ref.name();
-    let remote_ref_name =
-        match repo.branch_remote_tracking_ref_name(local_branch, gix::remote::Direction::Fetch) {
-            Some(Ok(name)) => name,
-            Some(Err(e)) => {
-                debug!(error = %e, "Failed to get remote tracking ref");
-                return RepoStatus::Clean;
+/// Which of `tips` are in the history of `remote_tips`. Both sides are
+/// painted newest commit first, and the walk stops once nothing left to
+/// visit is local-only and the remote side has gone a day past the oldest
+/// local-only commit it could still reach, so its cost follows how far the
+/// local tips are from the remote ones rather than all of the remote's
+/// history. The day of slack covers commits dated before their parents;
+/// worse clock skew only ever reports a tip as unpushed that isn't.
+///
+/// A hidden walk in gix would paint all of the remote history up front,
+/// and still yields a tip that's behind a hidden one.
+fn reachable_from(
+    repo: &gix::Repository,
+    tips: &[gix::ObjectId],
+    remote_tips: &[gix::ObjectId],
+) -> HashSet<gix::ObjectId> {
+    const LOCAL: u8 = 1;
+    const REMOTE: u8 = 2;
+    const CLOCK_SKEW: i64 = 24 * 60 * 60;
+    if remote_tips.is_empty() {
+        return HashSet::new();
+    }
+    let commit_time = |id: gix::ObjectId| {
+        repo.find_commit(id)
+            .ok()
+            .and_then(|commit| commit.time().ok())
+            .map_or(0, |time| time.seconds)
+    };
+
+    let mut flags: HashMap<gix::ObjectId, u8> = HashMap::new();
+    // Ordered by commit time, then remote before local for the same time
+    let mut queue = std::collections::BinaryHeap::new();
+    for (ids, flag) in [(tips, LOCAL), (remote_tips, REMOTE)] {
+        for &id in ids {
+            let painted = flags.entry(id).or_default();
+            if *painted & flag == 0 {
+                *painted |= flag;
+                queue.push((commit_time(id), *painted & REMOTE != 0, id));
             }
-            None => {
-                debug!("No upstream branch configured");
-                return RepoStatus::Clean;
+        }
+    }
+    let mut oldest_local = i64::MAX;
+    while let Some(&(time, _, _)) = queue.peek() {
+        let local_queued = queue.iter().any(|(_, _, id)| flags[id] == LOCAL);
+        if !local_queued && time < oldest_local.saturating_sub(CLOCK_SKEW) {
+            break;
+        }
+        let Some((time, _, id)) = queue.pop() else {
+            break;
+        };
+        let flag = flags[&id];
+        if flag == LOCAL {
+            oldest_local = oldest_local.min(time);
+        }
+        let commit = match repo.find_commit(id) {
+            Ok(commit) => commit,
+            Err(e) => {
+                // A shallow or broken history just ends here
+                debug!(error = %e, %id, "Failed to read commit");
+                continue;
             }
         };
+        for parent in commit.parent_ids() {
+            let parent = parent.detach();
+            let painted = flags.entry(parent).or_default();
+            if *painted | flag != *painted {
+                *painted |= flag;
+                queue.push((commit_time(parent), *painted & REMOTE != 0, parent));
+            }
+        }
+    }
+    tips.iter()
+        .filter(|&&id| flags.get(&id).is_some_and(|flag| flag & REMOTE != 0))
+        .copied()
+        .collect()
+}
 
-    // Try to find the remote ref
-    let has_unpushed = match repo.find_reference(remote_ref_name.as_ref()) {
-        Ok(remote_ref) => {
-            let local_commit = match head_ref.id().object() {
-                Ok(obj) => obj.id,
-                Err(e) => {
-                    warn!(error = %e, "Failed to get local commit");
-                    return RepoStatus::Clean;
-                }
-            };
-
-            let remote_commit = match remote_ref.id().object() {
-                Ok(obj) => obj.id,
-                Err(e) => {
-                    warn!(error = %e, "Failed to get remote commit");
-                    return RepoStatus::Clean;
-                }
-            };
+/// Commits of the tags a remote is known to have: those listed in
+/// FETCH_HEAD by the last fetch (`git fetch --tags` lists all of them), and
+/// those a clone wrote to packed-refs, for as long as that file hasn't been
+/// rewritten since. Tags created locally are written loose, so they never
+/// show up in a clone's packed-refs.
+fn remote_tags(repo: &gix::Repository) -> Vec<gix::ObjectId> {
+    let git_dir = repo.common_dir();
+    let mut ids: Vec<gix::ObjectId> = Vec::new();
+    if let Ok(fetch_head) = std::fs::read_to_string(git_dir.join("FETCH_HEAD")) {
+        ids.extend(fetch_head.lines().filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let id = fields.next()?;
+            let description = fields.nth(1)?;
+            if !description.starts_with("tag ") {
+                return None;
+            }
+            gix::ObjectId::from_hex(id.as_bytes()).ok()
+        }));
+    }
+    if packed_by_clone(git_dir)
+        && let Ok(packed) = std::fs::read_to_string(git_dir.join("packed-refs"))
+    {
+        ids.extend(packed.lines().filter_map(|line| {
+            let (id, name) = line.split_once(' ')?;
+            if !name.starts_with("refs/tags/") {
+                return None;
+            }
+            gix::ObjectId::from_hex(id.as_bytes()).ok()
+        }));
+    }
+    ids.into_iter()
+        .filter_map(|id| Some(repo.find_object(id).ok()?.peel_to_commit().ok()?.id))
+        .collect()
+}
 
-            local_commit != remote_commit
-        }
-        Err(_) => {
-            debug!("Remote ref not found, assuming no unpushed commits");
-            false
-        }
+/// Whether packed-refs is still the one written by the clone that created
+/// this repo, going by the time of the clone's entry in the HEAD reflog.
+fn packed_by_clone(git_dir: &Path) -> bool {
+    let Ok(log) = std::fs::read_to_string(git_dir.join("logs/HEAD")) else {
+        return false;
     };
-
-    if has_unpushed {
-        RepoStatus::Unpushed
-    } else {
-        RepoStatus::Clean
+    let Some((entry, message)) = log.lines().next().and_then(|line| line.split_once('\t')) else {
+        return false;
+    };
+    if !message.starts_with("clone: ") {
+        return false;
     }
+    // "<old> <new> <name> <email> <seconds> <offset>"
+    let Some(cloned) = entry
+        .rsplit(' ')
+        .nth(1)
+        .and_then(|seconds| seconds.parse::<u64>().ok())
+    else {
+        return false;
+    };
+    let Ok(written) = std::fs::metadata(git_dir.join("packed-refs")).and_then(|m| m.modified())
+    else {
+        return false;
+    };
+    let written = written
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    // The clone writes packed-refs just before checking out and logging HEAD
+    written <= cloned + 5
+}
+
+/// Milliseconds since the Unix epoch, as the library index and status cache
+/// record times
+pub(crate) fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Write a file through a temporary sibling renamed into place, so a
+/// concurrent reader, possibly in another workspace, never sees a partial
+/// file
+pub(crate) fn write_atomically(path: &Path, contents: &str) -> Result<()> {
+    use anyhow::Context;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(path.file_name().unwrap_or_default());
+    tmp_name.push(format!(".{}", std::process::id()));
+    let tmp = path.with_file_name(tmp_name);
+    std::fs::write(&tmp, contents).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
 }
 
 /// Format a SystemTime as a human-readable "time ago" string
@@ -408,87 +915,41 @@ pub fn get_repo_modification_time(repo_path: &Path) -> Result<std::time::SystemT
 
 /// Get the last commit time using gix
 fn get_last_commit_time(repo: &gix::Repository) -> Result<std::time::SystemTime> {
-    let Some(head_ref) = head_referent(repo) else {
+    let Ok(commit) = repo.head_commit() else {
         bail!("Repository has no commits");
     };
 
-    let commit = head_ref.id().object()?.try_into_commit()?;
     let timestamp = commit.time()?.seconds;
 
     Ok(std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(timestamp as u64))
 }
 
-/// Scan the worktree once, returning whether any changes or untracked files
-/// exist and the most recent modification time among them
-fn scan_worktree_changes(
-    repo: &gix::Repository,
-    repo_path: &Path,
-) -> (bool, std::time::SystemTime) {
-    let mut has_changes = false;
-    let mut latest_time = std::time::SystemTime::UNIX_EPOCH;
-
-    let platform = match repo.status(gix::progress::Discard) {
-        Ok(p) => p,
-        Err(e) => {
-            warn!(
-                path = %repo_path.display(),
-                error = %e,
-                "Failed to create status platform"
-            );
-            return (has_changes, latest_time);
-        }
-    };
-
-    // Iterate both tracked changes and untracked files in one pass
-    match platform
-        .untracked_files(gix::status::UntrackedFiles::Files)
-        .into_index_worktree_iter(Vec::new())
-    {
-        Ok(iter) => {
-            for item in iter.flatten() {
-                has_changes = true;
-                let file_path = repo_path.join(gix::path::from_bstr(item.rela_path()));
-                if let Ok(metadata) = std::fs::metadata(&file_path)
-                    && let Ok(modified) = metadata.modified()
-                    && modified > latest_time
-                {
-                    latest_time = modified;
-                }
-            }
-        }
-        Err(e) => {
-            warn!(
-                path = %repo_path.display(),
-                error = %e,
-                "Failed to check for changes"
-            );
-        }
-    }
-
-    (has_changes, latest_time)
-}
-
 /// A `Workspace` is filesystem directory containing git repositories checked out
 /// from one or more providers. Each repository's path matches the remote's path,
 /// for example:
 ///     <workspace path>/github.com/fossable/workset
 ///
-/// Workspace root is identified by the presence of a .workset/ directory.
+/// Workspace root is identified by the presence of a .workset/ directory,
+/// which also holds the workspace's config file.
 #[derive(Clone, Debug)]
 pub struct Workspace {
     /// The workspace directory's filesystem path
     pub path: String,
+
+    /// Settings from .workset/config.toml
+    pub config: config::Config,
 }
 
 impl Workspace {
     /// Get the library path for this workspace
     pub fn library_path(&self) -> String {
-        format!("{}/.workset", self.path)
+        self.library_path_buf().display().to_string()
     }
 
-    /// Get the library path as a PathBuf (avoids allocation)
-    fn library_path_buf(&self) -> PathBuf {
-        PathBuf::from(&self.path).join(".workset")
+    /// Get the library path as a PathBuf. The library may be outside the
+    /// workspace and shared with other workspaces.
+    pub fn library_path_buf(&self) -> PathBuf {
+        self.config.library_root(Path::new(&self.path))
     }
 
     /// Load workspace from current directory.
@@ -501,6 +962,7 @@ impl Workspace {
             if workset_dir.exists() && workset_dir.is_dir() {
                 let workspace = Workspace {
                     path: workspace_root.display().to_string(),
+                    config: config::Config::load(&workset_dir.join(config::CONFIG_FILE))?,
                 };
 
                 debug!(workspace_path = %workspace.path, "Found workspace");
@@ -508,9 +970,11 @@ impl Workspace {
                 // Validate the workspace configuration
                 workspace.validate()?;
 
-                // Make sure library directory exists
-                std::fs::create_dir_all(workspace.library_path_buf())
+                // Make sure library directory exists and knows about us
+                let library = workspace.library_path_buf();
+                std::fs::create_dir_all(&library)
                     .map_err(|e| anyhow::anyhow!("Failed to create library directory: {}", e))?;
+                library::register_workspace(&library, &workspace.path)?;
 
                 return Ok(Some(workspace));
             }
@@ -533,9 +997,36 @@ impl Workspace {
         Ok(())
     }
 
-    /// Search the workspace for local repos matching the given pattern.
-    pub fn search(&self, pattern: &RepoPattern) -> Result<Vec<PathBuf>> {
-        find_git_repositories(&Path::new(&self.path).join(pattern.full_path()))
+    /// Find the repos in the workspace matched by the pattern (see
+    /// [`RepoPattern::select`]). A relative path like `./repo` instead
+    /// selects the repo at that path or every repo beneath it.
+    pub fn search(&self, pattern: &RepoPattern, fuzzy: bool) -> Result<Vec<PathBuf>> {
+        let root = Path::new(&self.path);
+        if pattern.is_relative_path() {
+            let Ok(path) = std::env::current_dir()?.join(&pattern.path).canonicalize() else {
+                return Ok(Vec::new());
+            };
+            if !path.starts_with(root.canonicalize()?) {
+                bail!("{} is outside the workspace", pattern.path);
+            }
+            return find_git_repositories(&path);
+        }
+
+        let repos = find_git_repositories(root)?;
+        let names: Vec<String> = repos
+            .iter()
+            .map(|repo| {
+                repo.strip_prefix(root)
+                    .unwrap_or(repo)
+                    .display()
+                    .to_string()
+            })
+            .collect();
+        Ok(pattern
+            .select(&names, fuzzy)?
+            .into_iter()
+            .map(|name| root.join(name))
+            .collect())
     }
 
     /// Clone/open a repository in this workspace
@@ -543,7 +1034,7 @@ impl Workspace {
         debug!(pattern = ?pattern, "Opening repos");
 
         // First check if repository already exists locally
-        let local_repos = self.search(pattern)?;
+        let local_repos = self.search(pattern, false)?;
 
         if !local_repos.is_empty() {
             return Ok(local_repos[0].clone());
@@ -555,7 +1046,17 @@ impl Workspace {
 
         if self.library_contains(&relative_path) {
             self.restore_from_library(&relative_path)?;
-            // TODO: fetch latest changes from upstream once the gix API is clearer
+            if self.config.restore.fetch {
+                let interrupt = std::sync::atomic::AtomicBool::new(false);
+                match sync::fetch_and_fast_forward(
+                    Path::new(&repo_path),
+                    &self.config.sync,
+                    &interrupt,
+                ) {
+                    Ok(outcome) => debug!(outcome = ?outcome, "Fetched restored repository"),
+                    Err(e) => warn!(error = %e, "Failed to fetch restored repository"),
+                }
+            }
             return Ok(PathBuf::from(repo_path));
         }
 
@@ -564,88 +1065,282 @@ impl Workspace {
         Ok(repo_path)
     }
 
-    /// Drop a repository from this workspace
-    pub fn drop(&self, pattern: &RepoPattern, delete: bool, force: bool) -> Result<()> {
+    /// Drop a repository from this workspace. With `shelve`, uncommitted work
+    /// is stored along with the repo and reapplied when it's restored.
+    pub fn drop(
+        &self,
+        pattern: &RepoPattern,
+        fuzzy: bool,
+        delete: bool,
+        force: bool,
+        shelve: bool,
+    ) -> Result<()> {
         debug!("Drop requested for pattern: {:?}", pattern);
 
-        let repos = self.search(pattern)?;
+        let repos = self.search(pattern, fuzzy)?;
 
         if repos.is_empty() {
-            warn!(pattern = %pattern.full_path(), "No repositories found matching pattern");
-            return Ok(());
+            return Err(Error::Usage(format!(
+                "No repositories found matching '{}'",
+                pattern.full_path()
+            ))
+            .into());
         }
 
-        for repo in repos {
-            self.drop_repo(&repo, delete, force)?;
-        }
-        Ok(())
+        self.drop_paths(&repos, delete, force, shelve)
     }
 
     /// Drop all repositories in the current directory
-    pub fn drop_all(&self, delete: bool, force: bool) -> Result<()> {
+    pub fn drop_all(&self, delete: bool, force: bool, shelve: bool) -> Result<()> {
         debug!("Drop all requested in current directory");
 
         let cwd = std::env::current_dir()?;
-        let mut dropped = 0;
-        let mut skipped = 0;
+        self.drop_paths(&find_git_repositories(&cwd)?, delete, force, shelve)
+    }
 
-        for repo in find_git_repositories(&cwd)? {
-            if self.drop_repo(&repo, delete, force)? {
-                dropped += 1;
-            } else {
-                skipped += 1;
+    /// Drop the repos at the given paths, each checked for unsaved work just
+    /// before it goes. Repos that are refused or fail don't stop the others.
+    /// Linked worktrees and nested repos are dropped before the repos they
+    /// belong to.
+    pub fn drop_paths(
+        &self,
+        repos: &[PathBuf],
+        delete: bool,
+        force: bool,
+        shelve: bool,
+    ) -> Result<()> {
+        let mut dropped = 0;
+        let mut failed = 0;
+        let mut refused = Vec::new();
+
+        // Linked worktrees go first, so their main repo can follow them, and
+        // nested repos go before the repos they're inside of
+        let (worktrees, mut repos): (Vec<&PathBuf>, Vec<&PathBuf>) =
+            repos.iter().partition(|repo| worktree_main(repo).is_some());
+        repos.sort_by_key(|repo| std::cmp::Reverse(repo.components().count()));
+        for repo in worktrees.into_iter().chain(repos) {
+            match self.drop_repo(repo, delete, force, shelve) {
+                Ok(None) => dropped += 1,
+                Ok(Some(refusal)) => refused.push(refusal),
+                Err(e) => {
+                    error!(repo = %self.relative_path(repo), error = %e, "Failed to drop repository");
+                    failed += 1;
+                }
             }
         }
 
         if dropped > 0 {
             info!(count = dropped, "Dropped repositories");
         }
-        if skipped > 0 {
-            warn!(
-                count = skipped,
-                "Skipped repositories - use --force to drop anyway"
-            );
+        if failed > 0 {
+            let total = dropped + failed + refused.len();
+            // The refusals still need saying, but the failures decide the exit
+            if let Err(e) = refused_to_drop(refused) {
+                error!("{}", e);
+            }
+            return Err(Error::PartialFailure { failed, total }.into());
         }
+        refused_to_drop(refused)
+    }
 
-        Ok(())
+    /// Path of a repo relative to the workspace root
+    fn relative_path(&self, repo: &Path) -> String {
+        repo.strip_prefix(&self.path)
+            .unwrap_or(repo)
+            .to_string_lossy()
+            .trim_start_matches('/')
+            .to_string()
     }
 
     /// Drop a single repository: store it in the library (unless deleting) and
-    /// remove it from the workspace. Returns false if the repo was skipped
-    /// because it has uncommitted or unpushed changes.
-    fn drop_repo(&self, repo: &Path, delete: bool, force: bool) -> Result<bool> {
+    /// remove it from the workspace. Returns why the repo was skipped, if it
+    /// was, like it or one of its submodules having uncommitted or unpushed
+    /// changes.
+    fn drop_repo(
+        &self,
+        repo: &Path,
+        delete: bool,
+        force: bool,
+        shelve: bool,
+    ) -> Result<Option<Refusal>> {
+        // The repo lives somewhere else, which a drop shouldn't reach into
+        if discovery::through_symlink(Path::new(&self.path), repo) {
+            return Ok(Some(self.refusal(
+                repo,
+                "is reached through a symlink; remove the link instead".to_string(),
+                false,
+            )));
+        }
+
+        if let Some(main) = worktree_main(repo) {
+            return self.drop_worktree(repo, &main, force, shelve);
+        }
+
+        // Moving the git directory away would break the worktrees using it
+        let worktrees = linked_worktrees(repo);
+        if !worktrees.is_empty() {
+            let worktrees: Vec<String> = worktrees.iter().map(|w| self.relative_path(w)).collect();
+            return Ok(Some(self.refusal(
+                repo,
+                format!(
+                    "has linked worktrees at {}; drop those first",
+                    worktrees.join(", ")
+                ),
+                false,
+            )));
+        }
+
+        // Replacing another workspace's copy in a shared library could lose it
+        if !delete && let Some(reason) = self.library_conflict(&self.relative_path(repo)) {
+            return Ok(Some(self.refusal(repo, reason, false)));
+        }
+
+        // Shelving makes uncommitted changes safe to drop
+        let shelved = shelve && check_repo_status(repo)? == RepoStatus::Dirty;
+        if shelved {
+            debug!(repo = %repo.display(), "Shelving uncommitted changes");
+            shelve_changes(repo)?;
+        }
+
+        let outcome = self.remove_unless_refused(repo, delete, force);
+        // The repo stays, so its changes go back where they were
+        if shelved
+            && !matches!(outcome, Ok(None))
+            && let Err(e) = unshelve_changes(repo)
+        {
+            if outcome.is_ok() {
+                return Err(e);
+            }
+            error!(
+                repo = %repo.display(),
+                error = %e,
+                "Failed to reapply shelved changes, which are kept in {}",
+                SHELF_REF
+            );
+        }
+        outcome
+    }
+
+    /// The part of a drop after shelving: refuse unless forced when the repo
+    /// has work that would be lost, otherwise store it and remove it
+    fn remove_unless_refused(
+        &self,
+        repo: &Path,
+        delete: bool,
+        force: bool,
+    ) -> Result<Option<Refusal>> {
         // Check for uncommitted changes unless --force is given
         if !force {
-            match check_repo_status(repo)? {
-                RepoStatus::Dirty => {
-                    warn!(repo = %repo.display(), "Refusing to drop repository with uncommitted changes");
-                    warn!("Use --force to drop anyway");
-                    return Ok(false);
-                }
-                RepoStatus::Unpushed => {
-                    warn!(repo = %repo.display(), "Refusing to drop repository with unpushed commits");
-                    warn!("Use --force to drop anyway");
-                    return Ok(false);
+            // Submodules go first: their changes make the repo look dirty too
+            let submodules: Vec<String> = unsaved_submodules(repo)
+                .iter()
+                .map(|(path, _)| path.display().to_string())
+                .collect();
+            let reason = if !submodules.is_empty() {
+                Some(format!(
+                    "has uncommitted or unpushed work in submodule(s) {}",
+                    submodules.join(", ")
+                ))
+            } else {
+                match check_repo_status(repo)? {
+                    RepoStatus::Dirty => Some("has uncommitted changes".to_string()),
+                    RepoStatus::Unpushed => Some(format!(
+                        "has unpushed commits on {}",
+                        unpushed_refs(repo).unwrap_or_default().join(", ")
+                    )),
+                    _ => {
+                        let nested = self.unsaved_nested_repos(repo);
+                        (!nested.is_empty()).then(|| {
+                            format!(
+                                "has uncommitted or unpushed work in nested repo(s) {}",
+                                nested.join(", ")
+                            )
+                        })
+                    }
                 }
-                _ => {}
+            };
+            if let Some(reason) = reason {
+                return Ok(Some(self.refusal(repo, reason, true)));
             }
         }
 
         if !delete {
             // Store the repository in the library using workspace-relative path
-            let relative_path = repo
-                .strip_prefix(&self.path)
-                .unwrap_or(repo)
-                .to_string_lossy()
-                .trim_start_matches('/')
-                .to_string();
-            self.store_in_library(&relative_path)?;
+            self.store_in_library(&self.relative_path(repo))?;
         }
 
         // Remove the directory
         debug!(path = ?repo, "Removing directory");
         std::fs::remove_dir_all(repo)?;
-        Ok(true)
+        Ok(None)
+    }
+
+    fn refusal(&self, repo: &Path, reason: String, forceable: bool) -> Refusal {
+        Refusal {
+            repo: self.relative_path(repo),
+            reason,
+            forceable,
+        }
+    }
+
+    /// Independent repos checked out inside a repo that have uncommitted
+    /// changes or unpushed commits, relative to the repo. Removing the repo
+    /// would take them with it, whether or not they're listed.
+    fn unsaved_nested_repos(&self, repo: &Path) -> Vec<String> {
+        discovery::Discovery::for_path(repo)
+            .nested_repos(repo)
+            .into_iter()
+            .filter(|nested| {
+                matches!(
+                    check_repo_status(nested),
+                    Ok(RepoStatus::Dirty | RepoStatus::Unpushed)
+                )
+            })
+            .map(|nested| {
+                nested
+                    .strip_prefix(repo)
+                    .unwrap_or(&nested)
+                    .display()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    /// Drop a linked worktree by removing it through its main repo. Its
+    /// branch and commits live in the main repo, so only uncommitted changes
+    /// can be lost, and there's nothing to store in the library.
+    fn drop_worktree(
+        &self,
+        repo: &Path,
+        main: &Path,
+        force: bool,
+        shelve: bool,
+    ) -> Result<Option<Refusal>> {
+        let dirty = check_repo_status(repo)? == RepoStatus::Dirty;
+        if dirty && !force {
+            let reason = if shelve {
+                // The shelf ref would be shared with the main repo
+                "has uncommitted changes, which can't be shelved in a linked worktree"
+            } else {
+                "has uncommitted changes"
+            };
+            return Ok(Some(self.refusal(repo, reason.to_string(), true)));
+        }
+
+        debug!(worktree = ?repo, main = ?main, "Removing linked worktree");
+        let path = repo.to_string_lossy();
+        let mut args = vec!["worktree", "remove"];
+        if dirty {
+            args.push("--force");
+        }
+        args.push(&path);
+        if main.exists() {
+            git(main, &args)?;
+        } else {
+            // Nothing left to unregister it from
+            std::fs::remove_dir_all(repo)?;
+        }
+        Ok(None)
     }
 
     /// Attempt to clone a repository from configured remotes or infer the clone URL
@@ -654,9 +1349,8 @@ impl Workspace {
         // Pattern could be:
         // - github.com/user/repo (with provider)
         // - user/repo (without provider, check configured remotes)
-        if let Some((provider, repo_path)) = pattern.provider_and_path() {
+        if let Some(clone_url) = pattern.clone_url(&self.config) {
             // Has provider like github.com/user/repo
-            let clone_url = format!("https://{}/{}", provider, repo_path);
             let dest_path = Path::new(&self.path).join(pattern.full_path());
 
             gix_clone(&clone_url, &dest_path)?;
@@ -682,44 +1376,34 @@ impl Workspace {
             anyhow::anyhow!("Failed to create library directory {}: {}", library_path, e)
         })?;
 
-        let source = format!("{}/{}/.git", self.path, relative_path);
+        let repo = Path::new(&self.path).join(relative_path);
+        if let Some(main) = worktree_main(&repo) {
+            bail!(
+                "{} is a linked worktree of {}, which holds its history",
+                relative_path,
+                main.display()
+            );
+        }
+        if let Some(reason) = self.library_conflict(relative_path) {
+            return Err(Error::Refused {
+                action: "store".to_string(),
+                repos: vec![Refusal {
+                    repo: relative_path.to_string(),
+                    reason,
+                    forceable: false,
+                }],
+            }
+            .into());
+        }
+        // Follow a gitdir file to wherever the git directory really is
+        let source = git_dir(&repo).display().to_string();
         let dest = format!("{}/{}", library_path, relative_path);
 
         // Verify the source .git directory exists
-        if std::fs::metadata(&source).is_err() {
+        if !Path::new(&source).is_dir() {
             bail!("Repository .git directory not found: {}", source);
         }
 
-        // Set core.bare=true by modifying the config file directly
-        let config_path = std::path::Path::new(&source).join("config");
-        let config_content = std::fs::read_to_string(&config_path)?;
-
-        // Simple approach: check if core.bare already exists and update it, or add it
-        let new_config = if config_content.contains("[core]") {
-            // Replace or add bare = true under [core]
-            if config_content.contains("bare =") || config_content.contains("bare=") {
-                config_content
-                    .lines()
-                    .map(|line| {
-                        if line.trim().starts_with("bare") {
-                            "\tbare = true".to_string()
-                        } else {
-                            line.to_string()
-                        }
-                    })
-                    .collect::<Vec<_>>()
-                    .join("\n")
-            } else {
-                // Add bare = true after [core]
-                config_content.replacen("[core]", "[core]\n\tbare = true", 1)
-            }
-        } else {
-            // Add [core] section with bare = true
-            format!("{}\n[core]\n\tbare = true\n", config_content)
-        };
-
-        std::fs::write(&config_path, new_config)?;
-
         debug!(source = %source, dest = %dest, "Storing repository in library");
 
         // Create parent directories in library if needed
@@ -728,20 +1412,61 @@ impl Workspace {
                 .map_err(|e| anyhow::anyhow!("Failed to create library parent directory: {}", e))?;
         }
 
-        // Clear the library entry if it exists (for re-storing)
+        // Clear the library entry if it exists, which is only a clone of the
+        // same remote with nothing unpushed
         if std::fs::metadata(&dest).is_ok() {
             debug!("Removing existing library entry: {}", dest);
             std::fs::remove_dir_all(&dest)
                 .map_err(|e| anyhow::anyhow!("Failed to remove existing library entry: {}", e))?;
         }
 
-        // Move the repository to the library
-        std::fs::rename(&source, &dest)
-            .map_err(|e| anyhow::anyhow!("Failed to move repository to library: {}", e))?;
+        // The library holds bare repos, so nothing mistakes them for a checkout
+        set_core_bare(Path::new(&source), true)?;
+
+        // Move the repository to the library (which may be on another filesystem)
+        if let Err(e) = library::move_dir(Path::new(&source), Path::new(&dest)) {
+            // The repo stays a checkout where it is
+            if let Err(e) = set_core_bare(Path::new(&source), false) {
+                debug!(error = %e, "Failed to unset core.bare");
+            }
+            bail!("Failed to move repository to library: {}", e);
+        }
+
+        // The repo is safe in the library at this point, so a failure to
+        // record its history shouldn't fail the drop
+        let original_path = format!("{}/{}", self.path, relative_path);
+        if let Err(e) =
+            library::record_drop(Path::new(&library_path), relative_path, &original_path)
+        {
+            warn!(repo = %relative_path, error = %e, "Failed to record drop in library index");
+        }
 
         Ok(())
     }
 
+    /// Why a repo can't be stored over the library entry at its path, if
+    /// there is one. The entry may be the only copy of another workspace's
+    /// work, so it's only replaced when it's a clone of one of the repo's
+    /// remotes with everything pushed.
+    fn library_conflict(&self, relative_path: &str) -> Option<String> {
+        let entry = self.library_path_buf().join(relative_path);
+        std::fs::symlink_metadata(&entry).ok()?;
+
+        let remotes = remote_urls(&Path::new(&self.path).join(relative_path));
+        let same_repo = remote_urls(&entry)
+            .values()
+            .any(|url| remotes.values().any(|remote| remote == url));
+        let pushed = unpushed_refs(&entry).is_ok_and(|refs| refs.is_empty());
+        if same_repo && pushed {
+            None
+        } else {
+            Some(
+                "is already in the library from another clone, which may hold other work"
+                    .to_string(),
+            )
+        }
+    }
+
     /// Restore a repository from the library to the workspace.
     /// relative_path: the relative path of the repo within the workspace (e.g. "github.com/user/repo")
     pub fn restore_from_library(&self, relative_path: &str) -> Result<()> {
@@ -757,56 +1482,50 @@ impl Workspace {
             );
         }
 
-        // Get all remotes from the bare repository using gix
-        let source_repo = gix::open(&source)?;
-        let names = source_repo.remote_names();
-        let mut remote_names = Vec::new();
-        for name in names.iter() {
-            if let Ok(s) = std::str::from_utf8(name.as_ref()) {
-                remote_names.push(s.to_string());
-            }
+        if std::fs::metadata(&dest).is_ok() {
+            bail!("Repository already exists in workspace: {}", relative_path);
         }
 
-        // Clone from the library using gix
-        gix_clone(&source, Path::new(&dest))?;
-
-        // Restore all original remote URLs by updating the config file
-        let dest_config_path = std::path::Path::new(&dest).join(".git/config");
-        let mut dest_config_content = std::fs::read_to_string(&dest_config_path)?;
+        // Put the original .git back, so local branches, stashes, hooks and
+        // config come back exactly as they were dropped
+        let git_dir = Path::new(&dest).join(".git");
+        std::fs::create_dir_all(&dest)?;
+        library::move_dir(Path::new(&source), &git_dir)
+            .map_err(|e| anyhow::anyhow!("Failed to move repository out of library: {}", e))?;
+
+        if let Err(e) = checkout_from_index(Path::new(&dest)) {
+            // Leave the library as it was rather than half a checkout
+            library::move_dir(&git_dir, Path::new(&source))?;
+            std::fs::remove_dir_all(&dest)?;
+            return Err(e.context(format!("Failed to check out {}", relative_path)));
+        }
 
-        for remote_name in &remote_names {
-            // Get the URL for this remote from the library
-            if let Ok(remote) = source_repo.find_remote(remote_name.as_str())
-                && let Some(url) = remote.url(gix::remote::Direction::Fetch)
-            {
-                let remote_url = url.to_bstring().to_string();
-                debug!(remote = %remote_name, url = %remote_url, "Restoring remote");
-
-                // Find and update the URL line for this remote
-                let remote_section = format!("[remote \"{}\"]", remote_name);
-                if let Some(section_start) = dest_config_content.find(&remote_section) {
-                    // Find the URL line after the section start
-                    if let Some(url_line_start) =
-                        dest_config_content[section_start..].find("url = ")
-                    {
-                        let abs_url_start = section_start + url_line_start;
-                        if let Some(line_end) = dest_config_content[abs_url_start..].find('\n') {
-                            let abs_line_end = abs_url_start + line_end;
-                            dest_config_content.replace_range(
-                                abs_url_start..abs_line_end,
-                                &format!("\turl = {}", remote_url),
-                            );
-                        }
-                    }
-                }
+        if has_shelf(Path::new(&dest)) {
+            debug!(repo = %relative_path, "Reapplying shelved changes");
+            if let Err(e) = unshelve_changes(Path::new(&dest)) {
+                warn!(
+                    repo = %relative_path,
+                    error = %e,
+                    "Failed to reapply shelved changes, they're kept in {}",
+                    SHELF_REF
+                );
             }
         }
 
-        std::fs::write(&dest_config_path, dest_config_content)?;
+        if let Err(e) = library::record_restore(Path::new(&library_path), relative_path) {
+            warn!(repo = %relative_path, error = %e, "Failed to record restore in library index");
+        }
 
         Ok(())
     }
 
+    /// List the repositories in the library, most recently dropped first
+    pub fn list_library_by_recency(&self) -> Result<Vec<String>> {
+        let mut repos = self.list_library()?;
+        library::LibraryIndex::load(&self.library_path_buf())?.sort_by_recency(&mut repos);
+        Ok(repos)
+    }
+
     /// List all repositories in the library
     pub fn list_library(&self) -> Result<Vec<String>> {
         let library_path = self.library_path();
@@ -850,6 +1569,18 @@ impl Workspace {
     }
 }
 
+/// Turn the repos a drop left alone into an error, if there were any
+fn refused_to_drop(refused: Vec<Refusal>) -> Result<()> {
+    if refused.is_empty() {
+        return Ok(());
+    }
+    Err(Error::Refused {
+        action: "drop".to_string(),
+        repos: refused,
+    }
+    .into())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -862,6 +1593,7 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let workspace = Workspace {
             path: temp_dir.path().to_string_lossy().to_string(),
+            config: config::Config::default(),
         };
 
         let repo_path = "test/repo";
@@ -874,6 +1606,75 @@ mod tests {
         assert!(workspace.library_contains(repo_path));
     }
 
+    #[test]
+    fn test_set_core_bare() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config");
+        fs::write(
+            &config_path,
+            "[core]\n\tbare = false\n[user]\n\tname = Test\n",
+        )
+        .unwrap();
+
+        set_core_bare(temp_dir.path(), true).unwrap();
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            "[core]\n\tbare = true\n[user]\n\tname = Test\n"
+        );
+        set_core_bare(temp_dir.path(), false).unwrap();
+        assert!(
+            fs::read_to_string(&config_path)
+                .unwrap()
+                .contains("\tbare = false\n")
+        );
+
+        fs::write(&config_path, "[user]\n\tname = Test\n").unwrap();
+        set_core_bare(temp_dir.path(), true).unwrap();
+        assert!(
+            fs::read_to_string(&config_path)
+                .unwrap()
+                .ends_with("[core]\n\tbare = true\n")
+        );
+    }
+
+    #[test]
+    fn test_gitdir_files_and_worktrees() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path();
+
+        // A repo whose git directory lives elsewhere
+        let separate = base_path.join("separate");
+        fs::create_dir_all(base_path.join("gitdirs/separate")).unwrap();
+        fs::create_dir_all(&separate).unwrap();
+        fs::write(separate.join(".git"), "gitdir: ../gitdirs/separate\n").unwrap();
+        assert_eq!(git_dir(&separate), base_path.join("gitdirs/separate"));
+        assert_eq!(worktree_main(&separate), None);
+
+        let main = base_path.join("main");
+        fs::create_dir_all(&main).unwrap();
+        git(&main, &["init", "-q"]).unwrap();
+        test_util::git(
+            &main,
+            &["commit", "-q", "--allow-empty", "-m", "Initial commit"],
+        );
+        let worktree = base_path.join("feature");
+        git(
+            &main,
+            &["worktree", "add", "-q", "-b", "feature", "../feature"],
+        )
+        .unwrap();
+
+        assert_eq!(git_dir(&main), main.join(".git"));
+        assert_eq!(worktree_main(&main), None);
+        assert_eq!(git_dir(&worktree), main.join(".git/worktrees/feature"));
+        assert_eq!(common_git_dir(&worktree), main.join(".git"));
+        assert_eq!(worktree_main(&worktree), Some(main.clone()));
+        assert_eq!(linked_worktrees(&main), vec![worktree.clone()]);
+
+        fs::remove_dir_all(&worktree).unwrap();
+        assert!(linked_worktrees(&main).is_empty());
+    }
+
     #[test]
     fn test_find_git_repositories() {
         let temp_dir = TempDir::new().unwrap();
@@ -928,11 +1729,138 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_urls() -> Result<(), Box<dyn Error>> {
+        for url in [
+            "https://github.com/user/repo",
+            "https://github.com/user/repo.git",
+            "https://github.com/user/repo/",
+            "git@github.com:user/repo.git",
+            "github.com:user/repo",
+            "ssh://git@github.com/user/repo",
+            "ssh://git@github.com:2222/user/repo.git",
+            "git://github.com/user/repo.git",
+        ] {
+            let pattern = str::parse::<RepoPattern>(url)?;
+            assert_eq!(pattern.full_path(), "github.com/user/repo", "{url}");
+            assert_eq!(pattern.url.as_deref(), Some(url));
+            assert_eq!(
+                pattern.clone_url(&config::Config::default()).as_deref(),
+                Some(url)
+            );
+        }
+
+        let pattern = str::parse::<RepoPattern>("https://gitlab.com/group/sub/repo.git")?;
+        assert_eq!(pattern.provider, Some("gitlab.com".to_string()));
+        assert_eq!(pattern.path, "group/sub/repo");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_non_urls() -> Result<(), Box<dyn Error>> {
+        for path in [
+            "user/repo",
+            "github.com/user/repo",
+            "dir:with/colon",
+            "repo",
+        ] {
+            assert_eq!(str::parse::<RepoPattern>(path)?.url, None, "{path}");
+        }
+        let pattern = str::parse::<RepoPattern>("github.com/user/repo")?;
+        assert_eq!(
+            pattern.clone_url(&config::Config::default()).as_deref(),
+            Some("https://github.com/user/repo")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_matches_whole_trailing_segments() {
+        let Ok(pattern) = "jq".parse::<RepoPattern>();
+        assert!(pattern.matches("github.com/jqlang/jq"));
+        assert!(pattern.matches("jq"));
+        assert!(!pattern.matches("github.com/jquery/jquery"));
+        assert!(!pattern.matches("github.com/jq/other"));
+
+        let Ok(pattern) = "jqlang/jq".parse::<RepoPattern>();
+        assert!(pattern.matches("github.com/jqlang/jq"));
+        assert!(!pattern.matches("github.com/other/jq"));
+    }
+
+    #[test]
+    fn test_matches_globs() {
+        let Ok(pattern) = "github.com/user/*".parse::<RepoPattern>();
+        assert!(pattern.is_glob());
+        assert!(pattern.matches("github.com/user/a"));
+        assert!(!pattern.matches("github.com/user/a/b"));
+
+        let Ok(pattern) = "github.com/**".parse::<RepoPattern>();
+        assert!(pattern.matches("github.com/user/a"));
+        assert!(pattern.matches("github.com/org/group/b"));
+        assert!(!pattern.matches("gitlab.com/user/a"));
+
+        let Ok(pattern) = "**/tools/*-cli".parse::<RepoPattern>();
+        assert!(pattern.matches("gitlab.com/org/tools/fmt-cli"));
+        assert!(pattern.matches("tools/-cli"));
+        assert!(!pattern.matches("gitlab.com/org/tools/fmt"));
+
+        assert!(glob_segment("j?", "jq"));
+        assert!(glob_segment("*a*b", "xxaxxb"));
+        assert!(!glob_segment("*a*b", "xxaxxbc"));
+    }
+
+    #[test]
+    fn test_fuzzy_matches() {
+        let Ok(pattern) = "ghjq".parse::<RepoPattern>();
+        assert!(pattern.fuzzy_matches("GitHub.com/jqlang/jq"));
+        assert!(!pattern.fuzzy_matches("gitlab.com/jqlang/jq"));
+    }
+
+    #[test]
+    fn test_select_ambiguous() {
+        let candidates = [
+            "github.com/a/tool".to_string(),
+            "github.com/b/tool".to_string(),
+            "tool".to_string(),
+            "github.com/b/other".to_string(),
+        ];
+
+        // An exact path wins over other matches
+        let Ok(pattern) = "tool".parse::<RepoPattern>();
+        assert_eq!(pattern.select(&candidates, false).unwrap(), ["tool"]);
+
+        // Several matches without an exact one are listed instead
+        let Ok(pattern) = "b/*".parse::<RepoPattern>();
+        assert_eq!(pattern.select(&candidates, false).unwrap().len(), 2);
+        let Ok(pattern) = "github.com/b/tool".parse::<RepoPattern>();
+        assert_eq!(pattern.select(&candidates, false).unwrap().len(), 1);
+        let err = "tol"
+            .parse::<RepoPattern>()
+            .unwrap()
+            .select(&candidates, true)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("matches 3 repositories"), "{err}");
+        assert!(err.contains("github.com/a/tool"), "{err}");
+    }
+
+    #[test]
+    fn test_relative_path_patterns() {
+        for path in ["./repo", "../repo", ".", ".."] {
+            let Ok(pattern) = path.parse::<RepoPattern>();
+            assert!(pattern.is_relative_path(), "{path}");
+            assert_eq!(pattern.full_path(), path);
+        }
+        let Ok(pattern) = "github.com/user/repo".parse::<RepoPattern>();
+        assert!(!pattern.is_relative_path());
+    }
+
     #[test]
     fn test_provider_and_path() {
         let pattern = RepoPattern {
             provider: Some("github.com".to_string()),
             path: "user/repo".to_string(),
+            url: None,
         };
         let (provider, path) = pattern.provider_and_path().unwrap();
         assert_eq!(provider, "github.com");
@@ -944,6 +1872,7 @@ mod tests {
         let pattern = RepoPattern {
             provider: None,
             path: "user/repo".to_string(),
+            url: None,
         };
         assert!(pattern.provider_and_path().is_none());
     }
@@ -953,6 +1882,7 @@ mod tests {
         let pattern = RepoPattern {
             provider: Some("github.com".to_string()),
             path: "user/repo".to_string(),
+            url: None,
         };
         assert_eq!(pattern.full_path(), "github.com/user/repo");
     }
@@ -962,6 +1892,7 @@ mod tests {
         let pattern = RepoPattern {
             provider: None,
             path: "user/repo".to_string(),
+            url: None,
         };
         assert_eq!(pattern.full_path(), "user/repo");
     }
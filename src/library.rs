@@ -0,0 +1,355 @@
+//! Bookkeeping for the library directory.
+//!
+//! The library holds dropped repos as bare repositories keyed by their
+//! workspace-relative path, so several workspaces can point at the same
+//! library and trade repos through it. Each workspace registers itself in the
+//! library's index when it's loaded.
+//!
+//! The index also records when each repo was dropped and restored, so
+//! completions and the TUI can offer the most recently dropped repos first.
+//! Changes to it are made while holding a lock file next to it, so two
+//! workspaces dropping at once don't lose each other's records.
+//!
+//! The library may live on a different filesystem than the workspace, in
+//! which case repos are copied across instead of renamed.
+
+use crate::{now_millis, write_atomically};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// Name of the index file at the root of the library
+pub const INDEX_FILE: &str = "index.json";
+
+/// Name of the file that marks the index as being changed
+const LOCK_FILE: &str = "index.json.lock";
+
+/// How long to wait for another process to finish changing the index
+const LOCK_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A lock older than this was left behind by a process that died, since no
+/// change to the index takes anywhere near as long
+const STALE_LOCK: Duration = Duration::from_secs(10);
+
+/// Metadata stored alongside the repos in a library
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LibraryIndex {
+    /// Root paths of the workspaces using this library
+    pub workspaces: BTreeSet<String>,
+    /// History of each repo, keyed by its path in the library
+    pub repos: BTreeMap<String, RepoRecord>,
+}
+
+/// Drop and restore history of one repo in the library. Times are
+/// milliseconds since the Unix epoch.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RepoRecord {
+    pub dropped_at: Option<u64>,
+    pub restored_at: Option<u64>,
+    /// How many times the repo has been dropped
+    pub drop_count: u32,
+    /// Absolute path the repo was last dropped from
+    pub original_path: Option<String>,
+}
+
+impl RepoRecord {
+    /// When the repo was last dropped
+    pub fn dropped_time(&self) -> Option<SystemTime> {
+        self.dropped_at
+            .map(|ms| UNIX_EPOCH + Duration::from_millis(ms))
+    }
+}
+
+impl LibraryIndex {
+    /// Read the index of the library at the given path. A missing index is
+    /// treated as empty.
+    pub fn load(library: &Path) -> Result<Self> {
+        let path = library.join(INDEX_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid library index {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Write the index atomically
+    pub fn save(&self, library: &Path) -> Result<()> {
+        write_atomically(
+            &library.join(INDEX_FILE),
+            &serde_json::to_string_pretty(self)?,
+        )
+    }
+
+    /// Order library repo paths with the most recently dropped first. Repos
+    /// without a recorded drop come last, alphabetically.
+    pub fn sort_by_recency(&self, repos: &mut [String]) {
+        repos.sort_by(|a, b| {
+            let dropped_at = |repo: &String| self.repos.get(repo).and_then(|r| r.dropped_at);
+            dropped_at(b).cmp(&dropped_at(a)).then_with(|| a.cmp(b))
+        });
+    }
+}
+
+/// Exclusive access to a library's index, released when dropped
+struct IndexLock(PathBuf);
+
+impl IndexLock {
+    /// Wait until no other process is changing the index and take the lock
+    fn acquire(library: &Path) -> Result<Self> {
+        let path = library.join(LOCK_FILE);
+        let start = Instant::now();
+        loop {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => return Ok(Self(path)),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to create {}", path.display()));
+                }
+            }
+
+            let age = std::fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok());
+            if age.is_some_and(|age| age > STALE_LOCK) {
+                debug!(lock = %path.display(), "Removing stale library index lock");
+                let _ = std::fs::remove_file(&path);
+            } else if start.elapsed() > LOCK_TIMEOUT {
+                bail!(
+                    "Timed out waiting for {} (remove it if no workset is running)",
+                    path.display()
+                );
+            } else {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
+}
+
+impl Drop for IndexLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Load the index, apply a change and write it back, holding the lock
+/// throughout. The change returns whether there's anything to write.
+fn update_index(library: &Path, change: impl FnOnce(&mut LibraryIndex) -> bool) -> Result<()> {
+    let _lock = IndexLock::acquire(library)?;
+    let mut index = LibraryIndex::load(library)?;
+    if change(&mut index) {
+        index.save(library)?;
+    }
+    Ok(())
+}
+
+/// Record that a repo was dropped into the library from the given path
+pub fn record_drop(library: &Path, repo: &str, original_path: &str) -> Result<()> {
+    update_index(library, |index| {
+        let record = index.repos.entry(repo.to_string()).or_default();
+        record.dropped_at = Some(now_millis());
+        record.drop_count += 1;
+        record.original_path = Some(original_path.to_string());
+        true
+    })
+}
+
+/// Record that a repo was restored from the library
+pub fn record_restore(library: &Path, repo: &str) -> Result<()> {
+    update_index(library, |index| {
+        index.repos.entry(repo.to_string()).or_default().restored_at = Some(now_millis());
+        true
+    })
+}
+
+/// Record that the given workspace uses the library, if it isn't already
+pub fn register_workspace(library: &Path, workspace_path: &str) -> Result<()> {
+    // Most loads find the workspace registered already, so there's no need
+    // to wait for the lock then
+    if LibraryIndex::load(library)?
+        .workspaces
+        .contains(workspace_path)
+    {
+        return Ok(());
+    }
+    update_index(library, |index| {
+        let added = index.workspaces.insert(workspace_path.to_string());
+        if added {
+            debug!(library = %library.display(), workspace = %workspace_path, "Registering workspace with library");
+        }
+        added
+    })
+}
+
+/// Move a directory, falling back to copy-and-delete when the source and
+/// destination are on different filesystems
+pub fn move_dir(source: &Path, dest: &Path) -> Result<()> {
+    match std::fs::rename(source, dest) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            debug!(source = %source.display(), dest = %dest.display(), "Copying across filesystems");
+            copy_then_remove(source, dest)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Copy into a temporary sibling of `dest` and rename it into place, so an
+/// interrupted copy never leaves a partial entry behind; then delete the
+/// source
+fn copy_then_remove(source: &Path, dest: &Path) -> Result<()> {
+    let mut tmp_name = dest.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(".partial-{}", std::process::id()));
+    let tmp = dest.with_file_name(tmp_name);
+
+    if let Err(e) = copy_dir(source, &tmp) {
+        let _ = std::fs::remove_dir_all(&tmp);
+        return Err(e);
+    }
+    std::fs::rename(&tmp, dest)?;
+    std::fs::remove_dir_all(source)?;
+    Ok(())
+}
+
+/// Recursively copy a directory tree, preserving symlinks and permissions
+fn copy_dir(source: &Path, dest: &Path) -> Result<()> {
+    std::fs::create_dir_all(dest)?;
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target: PathBuf = dest.join(entry.file_name());
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            copy_symlink(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(source: &Path, dest: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(std::fs::read_link(source)?, dest)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(source: &Path, dest: &Path) -> Result<()> {
+    std::fs::copy(source, dest)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn register_workspace_is_idempotent() {
+        let library = TempDir::new().unwrap();
+        register_workspace(library.path(), "/home/me/work").unwrap();
+        register_workspace(library.path(), "/home/me/personal").unwrap();
+        register_workspace(library.path(), "/home/me/work").unwrap();
+
+        let index = LibraryIndex::load(library.path()).unwrap();
+        assert_eq!(
+            index.workspaces.into_iter().collect::<Vec<_>>(),
+            vec!["/home/me/personal", "/home/me/work"]
+        );
+    }
+
+    #[test]
+    fn drops_rank_by_recency() {
+        let library = TempDir::new().unwrap();
+        record_drop(library.path(), "a", "/ws/a").unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        record_drop(library.path(), "b", "/ws/b").unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        record_drop(library.path(), "a", "/other/a").unwrap();
+        record_restore(library.path(), "b").unwrap();
+
+        let index = LibraryIndex::load(library.path()).unwrap();
+        let a = &index.repos["a"];
+        assert_eq!(a.drop_count, 2);
+        assert_eq!(a.original_path.as_deref(), Some("/other/a"));
+        assert!(index.repos["b"].restored_at.is_some());
+
+        let mut repos = vec!["unknown".to_string(), "b".to_string(), "a".to_string()];
+        index.sort_by_recency(&mut repos);
+        assert_eq!(repos, ["a", "b", "unknown"]);
+    }
+
+    #[test]
+    fn concurrent_updates_are_all_kept() {
+        let library = TempDir::new().unwrap();
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let library = library.path();
+                scope.spawn(move || {
+                    let repo = format!("repo{}", i);
+                    record_drop(library, &repo, &format!("/ws/{}", repo)).unwrap();
+                });
+            }
+        });
+
+        let index = LibraryIndex::load(library.path()).unwrap();
+        assert_eq!(index.repos.len(), 8);
+        assert!(!library.path().join(LOCK_FILE).exists());
+    }
+
+    #[test]
+    fn stale_locks_are_taken_over() {
+        let library = TempDir::new().unwrap();
+        let lock = std::fs::File::create(library.path().join(LOCK_FILE)).unwrap();
+        lock.set_modified(SystemTime::now() - STALE_LOCK * 2)
+            .unwrap();
+
+        record_restore(library.path(), "a").unwrap();
+        assert!(
+            LibraryIndex::load(library.path()).unwrap().repos["a"]
+                .restored_at
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn copy_fallback_moves_whole_tree() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("src/.git");
+        std::fs::create_dir_all(source.join("refs/heads")).unwrap();
+        std::fs::write(source.join("config"), "[core]\n").unwrap();
+        std::fs::write(source.join("refs/heads/main"), "abc\n").unwrap();
+        let dest = temp.path().join("library/repo");
+        std::fs::create_dir_all(dest.parent().unwrap()).unwrap();
+
+        copy_then_remove(&source, &dest).unwrap();
+
+        assert!(!source.exists());
+        assert_eq!(
+            std::fs::read_to_string(dest.join("config")).unwrap(),
+            "[core]\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dest.join("refs/heads/main")).unwrap(),
+            "abc\n"
+        );
+        // No temporary entries are left next to the destination
+        assert_eq!(
+            std::fs::read_dir(dest.parent().unwrap()).unwrap().count(),
+            1
+        );
+    }
+}
@@ -1,9 +1,13 @@
 use anyhow::Result;
 use std::io::IsTerminal;
 use std::path::Path;
+use std::process::ExitCode;
+use std::sync::atomic::AtomicBool;
 use tracing::level_filters::LevelFilter;
 use tracing::{error, info};
 use workset::Workspace;
+use workset::cache::Freshness;
+use workset::scan::{Progress, for_each_parallel, scan_workspace, scan_workspace_sorted};
 
 /// ANSI color codes
 mod colors {
@@ -89,6 +93,7 @@ fn clone_repos(workspace: &Workspace, pattern: &workset::RepoPattern) -> Result<
 
             let mut cloned = 0;
             let mut skipped = 0;
+            let mut failed = 0;
 
             for repo in repos {
                 let Ok(repo_pattern) =
@@ -108,6 +113,7 @@ fn clone_repos(workspace: &Workspace, pattern: &workset::RepoPattern) -> Result<
                     }
                     Err(e) => {
                         error!(repo = %repo_pattern.full_path(), error = %e, "Failed to clone repository");
+                        failed += 1;
                     }
                 }
             }
@@ -117,6 +123,13 @@ fn clone_repos(workspace: &Workspace, pattern: &workset::RepoPattern) -> Result<
                 skipped = skipped,
                 "Completed cloning repositories"
             );
+            if failed > 0 {
+                return Err(workset::Error::PartialFailure {
+                    failed,
+                    total: cloned + skipped + failed,
+                }
+                .into());
+            }
             return Ok(());
         }
     }
@@ -145,8 +158,7 @@ fn clone_single_repo(workspace: &Workspace, pattern: &workset::RepoPattern) -> R
     }
 
     // Clone from remote
-    if let Some((provider, repo_path_str)) = pattern.provider_and_path() {
-        let clone_url = format!("https://{}/{}", provider, repo_path_str);
+    if let Some(clone_url) = pattern.clone_url(&workspace.config) {
         let dest_path = PathBuf::from(&workspace.path).join(pattern.full_path());
 
         info!(repo = %pattern.full_path(), "Cloning repository");
@@ -162,28 +174,31 @@ fn clone_single_repo(workspace: &Workspace, pattern: &workset::RepoPattern) -> R
 }
 
 /// Restore repositories from library matching the pattern
-fn restore_repos(workspace: &Workspace, pattern: &workset::RepoPattern) -> Result<()> {
+fn restore_repos(
+    workspace: &Workspace,
+    pattern: &workset::RepoPattern,
+    fuzzy: bool,
+    fetch: bool,
+) -> Result<()> {
     use std::path::PathBuf;
 
     // Get all repos from library
     let library_repos = workspace.list_library()?;
 
     if library_repos.is_empty() {
-        info!("Library is empty");
-        return Ok(());
+        return Err(workset::Error::Usage("The library is empty".to_string()).into());
     }
 
     // Filter repos that match the pattern
     let pattern_str = pattern.full_path();
-    let matching_repos: Vec<String> = library_repos
-        .iter()
-        .filter(|repo| repo.contains(&pattern_str))
-        .cloned()
-        .collect();
+    let matching_repos = pattern.select(&library_repos, fuzzy)?;
 
     if matching_repos.is_empty() {
-        info!(pattern = %pattern_str, "No repositories found in library matching pattern");
-        return Ok(());
+        return Err(workset::Error::Usage(format!(
+            "No repositories in the library match '{}'",
+            pattern_str
+        ))
+        .into());
     }
 
     info!(
@@ -193,6 +208,8 @@ fn restore_repos(workspace: &Workspace, pattern: &workset::RepoPattern) -> Resul
 
     let mut restored = 0;
     let mut skipped = 0;
+    let mut failed = 0;
+    let interrupt = std::sync::atomic::AtomicBool::new(false);
 
     for repo_path in matching_repos {
         // Check if already exists in workspace
@@ -202,13 +219,11 @@ fn restore_repos(workspace: &Workspace, pattern: &workset::RepoPattern) -> Resul
             continue;
         }
 
-        // Restore from library
-        match workspace.restore_from_library(&repo_path) {
-            Ok(_) => {
-                restored += 1;
-            }
+        match restore_repo(workspace, &repo_path, fetch, &interrupt) {
+            Ok(_) => restored += 1,
             Err(e) => {
                 error!(repo = %repo_path, error = %e, "Failed to restore repository");
+                failed += 1;
             }
         }
     }
@@ -218,10 +233,93 @@ fn restore_repos(workspace: &Workspace, pattern: &workset::RepoPattern) -> Resul
         skipped = skipped,
         "Completed restoring repositories"
     );
+    if failed > 0 {
+        return Err(workset::Error::PartialFailure {
+            failed,
+            total: restored + skipped + failed,
+        }
+        .into());
+    }
     Ok(())
 }
 
-fn main() -> Result<()> {
+/// Restore one repo from the library, then fetch it if asked to
+fn restore_repo(
+    workspace: &Workspace,
+    repo_path: &str,
+    fetch: bool,
+    interrupt: &std::sync::atomic::AtomicBool,
+) -> Result<()> {
+    workspace.restore_from_library(repo_path)?;
+    if fetch {
+        let dest_path = Path::new(&workspace.path).join(repo_path);
+        report_fetch(workspace, &dest_path, repo_path, interrupt);
+    }
+    Ok(())
+}
+
+/// Bring a freshly restored repo up to date with its remotes and say what
+/// changed. Nothing here fails the restore: the repo is usable either way.
+fn report_fetch(
+    workspace: &Workspace,
+    repo: &Path,
+    repo_name: &str,
+    interrupt: &std::sync::atomic::AtomicBool,
+) {
+    let outcome =
+        match workset::sync::fetch_and_fast_forward(repo, &workspace.config.sync, interrupt) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                println!("  {} - ⚠ fetch failed: {}", repo_name, e);
+                return;
+            }
+        };
+    if outcome.offline {
+        println!(
+            "  {} - ⚠ remotes unreachable, restored as stored",
+            repo_name
+        );
+        return;
+    }
+    for (remote, error) in &outcome.fetch_errors {
+        println!("  {} - ⚠ fetch {} failed: {}", repo_name, remote, error);
+    }
+    if !outcome.updated_refs.is_empty() {
+        println!(
+            "  {} - ✓ updated {}",
+            repo_name,
+            outcome.updated_refs.join(", ")
+        );
+    }
+    if let Some((branch, commits)) = &outcome.fast_forwarded {
+        println!(
+            "  {} - ✓ fast-forwarded {} by {} commit(s)",
+            repo_name, branch, commits
+        );
+    } else if let Some(reason) = &outcome.not_fast_forwarded {
+        println!("  {} - ⚠ not fast-forwarded: {}", repo_name, reason);
+    } else if outcome.updated_refs.is_empty() {
+        println!("  {} - ✓ up to date", repo_name);
+    }
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => match e.downcast_ref::<workset::Error>() {
+            Some(error) => {
+                eprintln!("Error: {}", error);
+                ExitCode::from(error.exit_code())
+            }
+            None => {
+                eprintln!("Error: {:?}", e);
+                ExitCode::FAILURE
+            }
+        },
+    }
+}
+
+fn run() -> Result<()> {
     // Initialize logging
     tracing_subscriber::fmt()
         .with_env_filter(
@@ -255,32 +353,64 @@ fn main() -> Result<()> {
   Manage git repos with working sets.
 
 {usage_header}
-  {cmd}workset{reset} init
+  {cmd}workset{reset} init [--library <path>]
   {cmd}workset{reset} clone <repo pattern>
-  {cmd}workset{reset} restore <repo pattern>
-  {cmd}workset{reset} drop [repo pattern] [--delete] [--force]
+  {cmd}workset{reset} restore <repo pattern> [--fuzzy] [--fetch | --no-fetch]
+  {cmd}workset{reset} drop [repo pattern] [--fuzzy] [--delete] [--force] [--shelve]
+  {cmd}workset{reset} apply <manifest> [--dry-run] [--prune]
+  {cmd}workset{reset} prune [--older-than <age>] [--dry-run] [--yes] [--delete]
 
 {commands_header}
-  {subcmd}init{reset}                                 Initialize a workspace in current directory
+  {subcmd}init{reset} {arg}[--library <path>]{reset}              Initialize a workspace in current directory
+{dim}                                       With --library: keep dropped repos there (can be shared){reset}
   {subcmd}clone{reset} {arg}<pattern>{reset}                      Clone new repository(ies) to workspace
   {subcmd}restore{reset} {arg}<pattern>{reset}                    Restore repository(ies) from library
-  {subcmd}drop{reset} {arg}[pattern]{reset} {arg}[--delete]{reset} {arg}[--force]{reset}  Drop repository(ies) from workspace
+{dim}                                       Patterns match whole trailing path segments, with
+                                       * and ? globs in a segment and ** across segments.
+                                       With --fuzzy: match letters in order instead
+                                       With --fetch: fetch remotes and fast-forward
+                                       With --no-fetch: don't, even if restore.fetch is set{reset}
+  {subcmd}drop{reset} {arg}[pattern]{reset}                       Drop repository(ies) from workspace
 {dim}                                       Without pattern: drops all in current directory
+                                       With --fuzzy: match letters in order instead
                                        With --delete: permanently delete (don't store)
-                                       With --force: drop even with uncommitted changes{reset}
-  {subcmd}list{reset}, {subcmd}ls{reset}                             List all repositories with their status
+                                       With --force: drop even with uncommitted changes
+                                       With --shelve: keep uncommitted changes for restore{reset}
+  {subcmd}apply{reset} {arg}<manifest>{reset}                     Clone or restore the repos a manifest lists
+{dim}                                       With --dry-run: only print the plan
+                                       With --prune: also drop clean repos not listed{reset}
+  {subcmd}prune{reset} {arg}[--older-than <age>]{reset}           Drop clean repos untouched for a while
+{dim}                                       Ages look like 30d, 2w or 6mo (default: prune.older_than)
+                                       Repos matching prune.pinned are never pruned
+                                       With --dry-run: only list the stale repos
+                                       With --yes: don't ask for confirmation
+                                       With --delete: permanently delete (don't store){reset}
+  {subcmd}list{reset}, {subcmd}ls{reset} {arg}[--library]{reset}                 List all repositories with their status
+{dim}                                       With --library: list the library instead{reset}
   {subcmd}status{reset}                               Show workspace summary and statistics
+  {subcmd}du{reset}                                   Show how much disk space each repo takes, largest first
+{dim}                                       Split into tracked files, the git directory, and
+                                       untracked or ignored files like build output{reset}
   {subcmd}sync{reset} {arg}[pattern]{reset}                       Mirror pushed commits to all of each repo's remotes
+{dim}                                       list, status, du and sync accept --json, or --ndjson
+                                       for one JSON object per line as results arrive.
+                                       list and status reuse recent results from
+                                       .workset; --refresh rescans every repo{reset}
 
 {examples_header}
   {cmd}workset init{reset}                              Initialize workspace here
+  {cmd}workset init --library ~/.workset{reset}         Initialize workspace with a shared library
   {cmd}workset clone github.com/user/repo{reset}        Clone a new repository
   {cmd}workset clone github.com/user{reset}             Clone all repos from github.com/user
+  {cmd}workset clone git@github.com:user/repo.git{reset} Clone from a URL into github.com/user/repo
   {cmd}workset restore repo{reset}                      Restore 'repo' from library
+  {cmd}workset restore 'github.com/user/*'{reset}       Restore every repo of github.com/user
   {cmd}workset drop ./repo{reset}                       Drop repo (save to library)
   {cmd}workset drop{reset}                              Drop all repos in current dir
   {cmd}workset drop --delete ./old_repo{reset}          Permanently delete a repo
   {cmd}workset drop --force ./dirty_repo{reset}         Force drop repo and lose any changes
+  {cmd}workset drop --shelve ./wip_repo{reset}          Drop repo, keeping unfinished work for restore
+  {cmd}workset prune --older-than 30d{reset}            Drop every clean repo untouched for 30 days
 "#,
             workset = if is_tty {
                 format!("{}{}{}", colors::BOLD, "workset", colors::RESET)
@@ -333,15 +463,22 @@ fn main() -> Result<()> {
         Some(command) => match command.as_str() {
             "init" => {
                 let workspace_path = std::env::current_dir()?;
-                let library_path = workspace_path.join(".workset");
+                let workset_dir = workspace_path.join(".workset");
 
-                if library_path.exists() {
+                if workset_dir.exists() {
                     info!(
                         path = %workspace_path.display(),
                         "Workspace already initialized"
                     );
                 } else {
-                    std::fs::create_dir_all(&library_path)?;
+                    let library = args.opt_value_from_str::<_, String>("--library")?;
+                    std::fs::create_dir_all(&workset_dir)?;
+                    std::fs::write(
+                        workset_dir.join(workset::config::CONFIG_FILE),
+                        workset::config::initial_config(library.as_deref()),
+                    )?;
+                    // Loading creates the library and registers us with it
+                    Workspace::load()?;
                     info!(path = %workspace_path.display(), "Initialized workspace");
                 }
             }
@@ -351,67 +488,144 @@ fn main() -> Result<()> {
                         let Ok(pattern) = pattern_str.parse::<workset::RepoPattern>();
                         clone_repos(&workspace, &pattern)?;
                     } else {
-                        error!("Missing repository pattern for clone command");
-                        error!("Usage: workset clone <pattern>");
+                        return Err(workset::Error::Usage(
+                            "Missing repository pattern. Usage: workset clone <pattern>"
+                                .to_string(),
+                        )
+                        .into());
                     }
                 } else {
-                    error!("Not in a workspace");
+                    return Err(workset::Error::NotInWorkspace.into());
                 }
             }
             "restore" => {
                 if let Some(workspace) = maybe_workspace {
+                    let fuzzy = args.contains("--fuzzy");
+                    let fetch = if args.contains("--no-fetch") {
+                        false
+                    } else {
+                        args.contains("--fetch") || workspace.config.restore.fetch
+                    };
                     if let Some(pattern_str) = args.opt_free_from_str::<String>()? {
                         let Ok(pattern) = pattern_str.parse::<workset::RepoPattern>();
-                        restore_repos(&workspace, &pattern)?;
+                        restore_repos(&workspace, &pattern, fuzzy, fetch)?;
                     } else {
-                        error!("Missing repository pattern for restore command");
-                        error!("Usage: workset restore <pattern>");
+                        return Err(workset::Error::Usage(
+                            "Missing repository pattern. Usage: workset restore <pattern>"
+                                .to_string(),
+                        )
+                        .into());
                     }
                 } else {
-                    error!("Not in a workspace");
+                    return Err(workset::Error::NotInWorkspace.into());
                 }
             }
             "drop" => {
                 if let Some(workspace) = maybe_workspace {
                     let delete = args.contains("--delete");
                     let force = args.contains("--force");
+                    let fuzzy = args.contains("--fuzzy");
+                    let shelve = args.contains("--shelve");
+                    if shelve && delete {
+                        return Err(workset::Error::Usage(
+                            "--shelve keeps changes in the library, so it can't be combined with --delete"
+                                .to_string(),
+                        )
+                        .into());
+                    }
 
                     if let Some(path) = args.opt_free_from_str::<String>()? {
                         let Ok(pattern) = path.parse::<workset::RepoPattern>();
-                        workspace.drop(&pattern, delete, force)?;
+                        workspace.drop(&pattern, fuzzy, delete, force, shelve)?;
                     } else {
                         // Drop all repos in current directory
-                        workspace.drop_all(delete, force)?;
+                        workspace.drop_all(delete, force, shelve)?;
+                    }
+                } else {
+                    return Err(workset::Error::NotInWorkspace.into());
+                }
+            }
+            "apply" => {
+                if let Some(workspace) = maybe_workspace {
+                    let dry_run = args.contains("--dry-run");
+                    let prune = args.contains("--prune");
+                    if let Some(manifest) = args.opt_free_from_str::<String>()? {
+                        apply_manifest(&workspace, Path::new(&manifest), dry_run, prune)?;
+                    } else {
+                        return Err(workset::Error::Usage(
+                            "Missing manifest. Usage: workset apply <manifest> [--dry-run] [--prune]"
+                                .to_string(),
+                        )
+                        .into());
                     }
                 } else {
-                    error!("Not in a workspace");
+                    return Err(workset::Error::NotInWorkspace.into());
+                }
+            }
+            "prune" => {
+                if let Some(workspace) = maybe_workspace {
+                    let dry_run = args.contains("--dry-run");
+                    let yes = args.contains("--yes");
+                    let delete = args.contains("--delete");
+                    let older_than = match args.opt_value_from_str::<_, String>("--older-than")? {
+                        Some(age) => workset::prune::parse_age(&age)
+                            .map_err(|e| workset::Error::Usage(e.to_string()))?,
+                        None => workspace.config.prune.older_than(),
+                    };
+                    prune_repos(&workspace, older_than, dry_run, yes, delete)?;
+                } else {
+                    return Err(workset::Error::NotInWorkspace.into());
                 }
             }
             "list" | "ls" => {
                 if let Some(workspace) = maybe_workspace {
-                    list_workspace_status(&workspace)?;
+                    let format = OutputFormat::from_args(&mut args);
+                    let freshness = freshness_from_args(&workspace, &mut args);
+                    if args.contains("--library") {
+                        list_library(&workspace, format)?;
+                    } else {
+                        list_workspace_status(&workspace, format, freshness)?;
+                    }
                 } else {
-                    error!("Not in a workspace");
+                    return Err(workset::Error::NotInWorkspace.into());
                 }
             }
             "status" => {
                 if let Some(workspace) = maybe_workspace {
-                    show_workspace_summary(&workspace)?;
+                    let format = OutputFormat::from_args(&mut args);
+                    let freshness = freshness_from_args(&workspace, &mut args);
+                    show_workspace_summary(&workspace, format, freshness)?;
                 } else {
-                    error!("Not in a workspace");
+                    return Err(workset::Error::NotInWorkspace.into());
+                }
+            }
+            "du" => {
+                if let Some(workspace) = maybe_workspace {
+                    let format = OutputFormat::from_args(&mut args);
+                    show_disk_usage(&workspace, format)?;
+                } else {
+                    return Err(workset::Error::NotInWorkspace.into());
                 }
             }
             "sync" => {
                 if let Some(workspace) = maybe_workspace {
-                    let pattern = args.opt_free_from_str::<String>()?;
-                    sync_repos(&workspace, pattern.as_deref())?;
+                    let fuzzy = args.contains("--fuzzy");
+                    let format = OutputFormat::from_args(&mut args);
+                    let pattern = args
+                        .opt_free_from_str::<String>()?
+                        .map(|p| p.parse::<workset::RepoPattern>())
+                        .transpose()?;
+                    sync_repos(&workspace, pattern.as_ref(), fuzzy, format)?;
                 } else {
-                    error!("Not in a workspace");
+                    return Err(workset::Error::NotInWorkspace.into());
                 }
             }
             _ => {
-                error!(command = %command, "Unknown command");
-                error!("Run 'workset --help' for usage information");
+                return Err(workset::Error::Usage(format!(
+                    "Unknown command '{}'. Run 'workset --help' for usage information",
+                    command
+                ))
+                .into());
             }
         },
         None => {
@@ -421,7 +635,7 @@ fn main() -> Result<()> {
                     // Open TUI for interactive workspace management
                     workset::tui::run_tui(&workspace)?;
                 } else {
-                    error!("Not in a workspace");
+                    return Err(workset::Error::NotInWorkspace.into());
                 }
             }
             #[cfg(not(feature = "tui"))]
@@ -433,10 +647,260 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Bring the workspace in line with a manifest, printing each step as it's
+/// carried out (or only printing them with `dry_run`)
+fn apply_manifest(
+    workspace: &Workspace,
+    manifest: &Path,
+    dry_run: bool,
+    prune: bool,
+) -> Result<()> {
+    use workset::manifest::{Manifest, Step};
+
+    let manifest = Manifest::load(manifest)?;
+    let steps = manifest.plan(workspace, prune)?;
+    if steps.is_empty() {
+        println!("Workspace matches the manifest");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    let interrupt = std::sync::atomic::AtomicBool::new(false);
+    for step in &steps {
+        println!("{}", step);
+        if dry_run {
+            continue;
+        }
+
+        let result = match step {
+            Step::Clone { path, url } => {
+                let Ok(mut pattern) = path.parse::<workset::RepoPattern>();
+                pattern.url = Some(url.clone());
+                clone_single_repo(workspace, &pattern)
+            }
+            Step::Restore { path } => {
+                restore_repo(workspace, path, workspace.config.restore.fetch, &interrupt)
+            }
+            Step::Remote {
+                path,
+                name,
+                url,
+                exists,
+            } => {
+                let action = if *exists { "set-url" } else { "add" };
+                run_git(workspace, path, &["remote", action, name, url])
+            }
+            Step::Checkout { path, branch } => {
+                run_git(workspace, path, &["checkout", "-q", branch])
+            }
+            Step::Drop { path } => {
+                let Ok(pattern) = path.parse::<workset::RepoPattern>();
+                workspace.drop(&pattern, false, false, false, false)
+            }
+            Step::Keep { .. } => Ok(()),
+        };
+        if let Err(e) = result {
+            error!(step = %step, error = %e, "Step failed");
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        return Err(workset::Error::PartialFailure {
+            failed,
+            total: steps.len(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Drop the clean repos that haven't been touched for `older_than`, after
+/// listing them and asking for confirmation (unless `yes`)
+fn prune_repos(
+    workspace: &Workspace,
+    older_than: std::time::Duration,
+    dry_run: bool,
+    yes: bool,
+    delete: bool,
+) -> Result<()> {
+    let stale = workset::prune::find_stale(
+        workspace,
+        Path::new(&workspace.path),
+        older_than,
+        show_progress,
+    )?;
+    if stale.is_empty() {
+        println!("No clean repositories untouched for that long");
+        return Ok(());
+    }
+
+    println!("Clean repositories untouched since the cutoff:");
+    for repo in &stale {
+        println!(
+            "  {} - last touched {} ago",
+            repo.repo,
+            workset::format_time_ago(repo.modified)
+        );
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    if !yes {
+        if !std::io::stdin().is_terminal() {
+            return Err(workset::Error::Usage(
+                "Not prompting without a terminal; pass --yes to prune".to_string(),
+            )
+            .into());
+        }
+        let what = if delete { "Delete" } else { "Drop" };
+        eprint!("{} {} repository(ies)? [y/N] ", what, stale.len());
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            println!("Nothing pruned");
+            return Ok(());
+        }
+    }
+
+    let paths: Vec<_> = stale.into_iter().map(|repo| repo.path).collect();
+    workspace.drop_paths(&paths, delete, false, false)?;
+    println!("Pruned {} repository(ies)", paths.len());
+    Ok(())
+}
+
+/// Run git in the given workspace repo
+fn run_git(workspace: &Workspace, repo: &str, args: &[&str]) -> Result<()> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(Path::new(&workspace.path).join(repo))
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// How a command prints its results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// Lines for people to read
+    Human,
+    /// One JSON document once the command finishes
+    Json,
+    /// One JSON object per line, printed as soon as each is ready
+    Ndjson,
+}
+
+impl OutputFormat {
+    fn from_args(args: &mut pico_args::Arguments) -> Self {
+        if args.contains("--ndjson") {
+            OutputFormat::Ndjson
+        } else if args.contains("--json") {
+            OutputFormat::Json
+        } else {
+            OutputFormat::Human
+        }
+    }
+}
+
+/// Prints records in a machine-readable format. With JSON the records are
+/// collected into one array sorted by path; with NDJSON each one is printed
+/// right away, in the order the repos finish.
+struct JsonRecords {
+    format: OutputFormat,
+    records: Vec<serde_json::Value>,
+}
+
+impl JsonRecords {
+    fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            records: Vec::new(),
+        }
+    }
+
+    fn emit(&mut self, record: serde_json::Value) {
+        match self.format {
+            OutputFormat::Ndjson => println!("{}", record),
+            _ => self.records.push(record),
+        }
+    }
+
+    fn finish(mut self) -> Result<()> {
+        if self.format == OutputFormat::Json {
+            self.records
+                .sort_by(|a, b| a["path"].as_str().cmp(&b["path"].as_str()));
+            println!("{}", serde_json::to_string_pretty(&self.records)?);
+        }
+        Ok(())
+    }
+}
+
+/// How much `list` and `status` may rely on the status cache
+fn freshness_from_args(workspace: &Workspace, args: &mut pico_args::Arguments) -> Freshness {
+    if args.contains("--refresh") {
+        Freshness::Refresh
+    } else {
+        Freshness::Within(workspace.config.status.cache_ttl())
+    }
+}
+
+/// Show how far a scan has got on stderr, when that's a terminal. The line
+/// is cleared once the scan completes.
+fn show_progress(progress: Progress) {
+    if !std::io::stderr().is_terminal() {
+        return;
+    }
+    if progress.done < progress.total {
+        eprint!("\r\x1b[2Kscanning {}/{}", progress.done, progress.total);
+    } else {
+        eprint!("\r\x1b[2K");
+    }
+}
+
+/// Seconds since the Unix epoch, for JSON output
+fn unix_time(time: Option<std::time::SystemTime>) -> Option<u64> {
+    time.and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
 /// List all repositories in the workspace with their status
-fn list_workspace_status(workspace: &Workspace) -> Result<()> {
-    let repos = workset::find_git_repositories(Path::new(&workspace.path))?;
+fn list_workspace_status(
+    workspace: &Workspace,
+    format: OutputFormat,
+    freshness: Freshness,
+) -> Result<()> {
+    let root = Path::new(&workspace.path);
+
+    if format != OutputFormat::Human {
+        let mut output = JsonRecords::new(format);
+        scan_workspace(root, freshness, &AtomicBool::new(false), |scan, _| {
+            let mut record = serde_json::json!({
+                "path": scan.repo,
+                "remotes": workset::remote_urls(&scan.path),
+            });
+            if let Some(main) = &scan.worktree_of {
+                record["worktree_of"] = main.as_str().into();
+            }
+            match scan.state {
+                Ok(Some(cached)) => {
+                    record["status"] = serde_json::json!(cached.state.status());
+                    record["modified"] = serde_json::json!(unix_time(cached.modified));
+                    record["scanned_at"] = serde_json::json!(unix_time(Some(cached.scanned_at)));
+                    record["cached"] = cached.cached.into();
+                    record["state"] = serde_json::json!(cached.state);
+                }
+                Ok(None) => record["error"] = "not a git repository".into(),
+                Err(e) => record["error"] = e.to_string().into(),
+            }
+            output.emit(record);
+        })?;
+        return output.finish();
+    }
 
+    let repos = scan_workspace_sorted(root, freshness, show_progress)?;
     if repos.is_empty() {
         println!("No repositories found in workspace");
         return Ok(());
@@ -445,31 +909,161 @@ fn list_workspace_status(workspace: &Workspace) -> Result<()> {
     println!("Repositories in workspace ({}):", workspace.path);
     println!();
 
-    for repo in repos {
-        let repo_name = repo
-            .strip_prefix(&workspace.path)
-            .unwrap_or(&repo)
-            .display()
-            .to_string();
-
-        let status_str = match workset::check_repo_status(&repo) {
-            Ok(workset::RepoStatus::Clean) => "✓ clean".to_string(),
-            Ok(workset::RepoStatus::Dirty) => "⚠ modified".to_string(),
-            Ok(workset::RepoStatus::Unpushed) => "⚠ unpushed".to_string(),
-            Ok(workset::RepoStatus::NoCommits) => "⚠ no commits".to_string(),
-            Err(_) => "✗ error".to_string(),
+    for scan in repos {
+        let status_str = match scan.state {
+            Ok(Some(cached)) => {
+                let state = &cached.state;
+                let mut line = match state.status() {
+                    workset::RepoStatus::Clean => "✓ clean".to_string(),
+                    workset::RepoStatus::Dirty => "⚠ modified".to_string(),
+                    workset::RepoStatus::Unpushed => {
+                        format!("⚠ unpushed ({})", state.unpushed.join(", "))
+                    }
+                    workset::RepoStatus::NoCommits => "⚠ no commits".to_string(),
+                };
+                // Everything else worth knowing, e.g. "· 2 staged · 3 behind"
+                for detail in state.details() {
+                    line.push_str(" · ");
+                    line.push_str(&detail);
+                }
+                if cached.cached {
+                    line.push_str(&format!(
+                        " · checked {} ago",
+                        workset::format_time_ago(cached.scanned_at)
+                    ));
+                }
+                line
+            }
+            _ => "✗ error".to_string(),
         };
 
-        println!("  {} - {}", repo_name, status_str);
+        // Linked worktrees are listed under the repo they were added from
+        match &scan.worktree_of {
+            Some(main) => println!("    {} - {} · worktree of {}", scan.repo, status_str, main),
+            None => println!("  {} - {}", scan.repo, status_str),
+        }
+    }
+
+    Ok(())
+}
+
+/// Show the disk usage of every repo in the workspace, largest first
+fn show_disk_usage(workspace: &Workspace, format: OutputFormat) -> Result<()> {
+    use workset::usage::format_size;
+
+    let repos = workset::usage::workspace_usage(workspace, Path::new(&workspace.path), |p| {
+        if format == OutputFormat::Human {
+            show_progress(p)
+        }
+    })?;
+
+    if format != OutputFormat::Human {
+        let mut output = JsonRecords::new(format);
+        for repo in repos {
+            let mut record = serde_json::json!({
+                "path": repo.repo,
+                "total": repo.usage.total(),
+                "worktree": repo.usage.worktree,
+                "git": repo.usage.git,
+                "artifacts": repo.usage.artifacts,
+            });
+            if repo.nested {
+                record["nested"] = true.into();
+            }
+            output.emit(record);
+        }
+        return output.finish();
+    }
+
+    if repos.is_empty() {
+        println!("No repositories found in workspace");
+        return Ok(());
+    }
+
+    println!("Disk usage in workspace ({}):", workspace.path);
+    println!();
+    for repo in &repos {
+        let usage = &repo.usage;
+        let mut line = format!(
+            "  {} - {} · {} tracked · {} git · {} untracked or ignored",
+            repo.repo,
+            format_size(usage.total()),
+            format_size(usage.worktree),
+            format_size(usage.git),
+            format_size(usage.artifacts),
+        );
+        if repo.nested {
+            line.push_str(" · inside another repo");
+        }
+        println!("{}", line);
+    }
+
+    // Repos inside other repos are part of those repos' usage already
+    let (count, total, artifacts) = repos.iter().filter(|repo| !repo.nested).fold(
+        (0, 0, 0),
+        |(count, total, artifacts), repo| {
+            (
+                count + 1,
+                total + repo.usage.total(),
+                artifacts + repo.usage.artifacts,
+            )
+        },
+    );
+    println!();
+    println!(
+        "Total: {} in {} repositories, {} of it untracked or ignored",
+        format_size(total),
+        count,
+        format_size(artifacts)
+    );
+    Ok(())
+}
+
+/// List the repositories stored in the library
+fn list_library(workspace: &Workspace, format: OutputFormat) -> Result<()> {
+    let repos = workspace.list_library()?;
+    let library = workspace.library_path_buf();
+
+    if format != OutputFormat::Human {
+        let mut output = JsonRecords::new(format);
+        for repo in repos {
+            output.emit(serde_json::json!({
+                "path": repo,
+                "remotes": workset::remote_urls(&library.join(&repo)),
+            }));
+        }
+        return output.finish();
+    }
+
+    if repos.is_empty() {
+        println!("No repositories found in library");
+        return Ok(());
     }
 
+    println!("Repositories in library ({}):", library.display());
+    println!();
+    for repo in repos {
+        println!("  {}", repo);
+    }
     Ok(())
 }
 
 /// Mirror pushed commits across each repo's remotes, printing per-ref results
-fn sync_repos(workspace: &Workspace, pattern: Option<&str>) -> Result<()> {
-    let repos = workset::find_git_repositories(Path::new(&workspace.path))?;
-    let interrupt = std::sync::atomic::AtomicBool::new(false);
+fn sync_repos(
+    workspace: &Workspace,
+    pattern: Option<&workset::RepoPattern>,
+    fuzzy: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    let repos = match pattern {
+        Some(pattern) => workspace.search(pattern, fuzzy)?,
+        // Linked worktrees share their main repo's refs and remotes
+        None => workset::find_git_repositories(Path::new(&workspace.path))?
+            .into_iter()
+            .filter(|repo| workset::worktree_main(repo).is_none())
+            .collect(),
+    };
+    let interrupt = AtomicBool::new(false);
     let short = |refname: &str| {
         refname
             .strip_prefix("refs/heads/")
@@ -477,70 +1071,176 @@ fn sync_repos(workspace: &Workspace, pattern: Option<&str>) -> Result<()> {
             .unwrap_or(refname)
             .to_string()
     };
-
-    let mut matched = false;
-    for repo in repos {
-        let repo_name = repo
-            .strip_prefix(&workspace.path)
-            .unwrap_or(&repo)
+    let repo_name = |repo: &Path| {
+        repo.strip_prefix(&workspace.path)
+            .unwrap_or(repo)
             .display()
-            .to_string();
-        if pattern.is_some_and(|p| !repo_name.contains(p)) {
-            continue;
+            .to_string()
+    };
+    // Repos sync concurrently, each result reported as soon as it's in
+    let sync_all = |on_result: &mut dyn FnMut(String, Result<workset::sync::SyncOutcome>)| {
+        for_each_parallel(
+            repos.iter(),
+            workspace.config.sync.max_concurrent,
+            &interrupt,
+            |repo| {
+                let outcome = workset::sync::sync_repo(repo, &workspace.config.sync, &interrupt);
+                (repo_name(repo), outcome)
+            },
+            |(repo_name, outcome), _| on_result(repo_name, outcome),
+        )
+    };
+
+    // Repos that couldn't be fully synced
+    let total = repos.len();
+    let mut failed = 0;
+    let sync_result = |failed| {
+        if failed > 0 {
+            return Err(workset::Error::PartialFailure { failed, total }.into());
         }
-        matched = true;
+        Ok(())
+    };
 
-        match workset::sync::sync_repo(&repo, &interrupt) {
-            Ok(outcome) => {
-                for (remote, refname) in &outcome.pushed {
-                    println!(
-                        "  {} - ✓ pushed {} to {}",
-                        repo_name,
-                        short(refname),
-                        remote
-                    );
-                }
-                for (remote, refname, reason) in &outcome.conflicts {
-                    println!(
-                        "  {} - ⚠ {} on {}: {}",
-                        repo_name,
-                        short(refname),
-                        remote,
-                        reason
-                    );
-                }
-                for (remote, refname, error) in &outcome.push_errors {
-                    println!(
-                        "  {} - ✗ push {} to {} failed: {}",
-                        repo_name,
-                        short(refname),
-                        remote,
-                        error
-                    );
-                }
-                for (remote, error) in &outcome.fetch_errors {
-                    println!("  {} - ✗ fetch {} failed: {}", repo_name, remote, error);
+    if format != OutputFormat::Human {
+        let mut output = JsonRecords::new(format);
+        sync_all(&mut |repo_name, outcome| {
+            let record = match outcome {
+                Ok(outcome) => {
+                    if outcome.error_summary().is_some() {
+                        failed += 1;
+                    }
+                    sync_outcome_json(&repo_name, &outcome)
                 }
-                if outcome.pushed.is_empty()
-                    && outcome.conflicts.is_empty()
-                    && outcome.push_errors.is_empty()
-                    && outcome.fetch_errors.is_empty()
-                {
-                    println!("  {} - ✓ in sync", repo_name);
+                Err(e) => {
+                    failed += 1;
+                    serde_json::json!({ "path": repo_name, "error": e.to_string() })
                 }
-            }
-            Err(e) => println!("  {} - ✗ sync failed: {}", repo_name, e),
-        }
+            };
+            output.emit(record);
+        });
+        output.finish()?;
+        return sync_result(failed);
     }
 
-    if !matched {
+    if repos.is_empty() {
         println!("No repositories matched");
+        return Ok(());
     }
-    Ok(())
+    sync_all(&mut |repo_name, outcome| match outcome {
+        Ok(outcome) => {
+            if outcome.error_summary().is_some() {
+                failed += 1;
+            }
+            for (remote, refname) in &outcome.pushed {
+                println!(
+                    "  {} - ✓ pushed {} to {}",
+                    repo_name,
+                    short(refname),
+                    remote
+                );
+            }
+            for (remote, refname, reason) in &outcome.conflicts {
+                println!(
+                    "  {} - ⚠ {} on {}: {}",
+                    repo_name,
+                    short(refname),
+                    remote,
+                    reason
+                );
+            }
+            for (remote, refname, error) in &outcome.push_errors {
+                println!(
+                    "  {} - ✗ push {} to {} failed: {}",
+                    repo_name,
+                    short(refname),
+                    remote,
+                    error
+                );
+            }
+            for (remote, error) in &outcome.fetch_errors {
+                println!("  {} - ✗ fetch {} failed: {}", repo_name, remote, error);
+            }
+            if outcome.pushed.is_empty()
+                && outcome.conflicts.is_empty()
+                && outcome.push_errors.is_empty()
+                && outcome.fetch_errors.is_empty()
+            {
+                println!("  {} - ✓ in sync", repo_name);
+            }
+        }
+        Err(e) => {
+            println!("  {} - ✗ sync failed: {}", repo_name, e);
+            failed += 1;
+        }
+    });
+
+    sync_result(failed)
+}
+
+/// Every field of a sync outcome, with the tuples spelled out as objects
+fn sync_outcome_json(repo_name: &str, outcome: &workset::sync::SyncOutcome) -> serde_json::Value {
+    serde_json::json!({
+        "path": repo_name,
+        "pushed": outcome.pushed.iter().map(|(remote, refname)| {
+            serde_json::json!({ "remote": remote, "ref": refname })
+        }).collect::<Vec<_>>(),
+        "conflicts": outcome.conflicts.iter().map(|(remote, refname, reason)| {
+            serde_json::json!({ "remote": remote, "ref": refname, "reason": reason })
+        }).collect::<Vec<_>>(),
+        "push_errors": outcome.push_errors.iter().map(|(remote, refname, error)| {
+            serde_json::json!({ "remote": remote, "ref": refname, "error": error })
+        }).collect::<Vec<_>>(),
+        "fetch_errors": outcome.fetch_errors.iter().map(|(remote, error)| {
+            serde_json::json!({ "remote": remote, "error": error })
+        }).collect::<Vec<_>>(),
+        "status": outcome.status,
+        "modified": unix_time(outcome.modification_time),
+    })
 }
 
 /// Show a summary of the workspace
-fn show_workspace_summary(workspace: &Workspace) -> Result<()> {
+fn show_workspace_summary(
+    workspace: &Workspace,
+    format: OutputFormat,
+    freshness: Freshness,
+) -> Result<()> {
+    if format != OutputFormat::Human {
+        let library_repos = workspace.list_library()?.len();
+        let shared_with =
+            workset::library::LibraryIndex::load(Path::new(&workspace.library_path()))?
+                .workspaces
+                .len()
+                .saturating_sub(1);
+        let mut counts = std::collections::BTreeMap::<String, usize>::new();
+        let repos = scan_workspace_sorted(Path::new(&workspace.path), freshness, |_| {})?;
+        for scan in &repos {
+            let status = match &scan.state {
+                Ok(Some(cached)) => serde_json::to_value(cached.state.status())?
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                Ok(None) => "no_commits".to_string(),
+                Err(_) => "error".to_string(),
+            };
+            *counts.entry(status).or_default() += 1;
+        }
+        let summary = serde_json::json!({
+            "workspace": workspace.path,
+            "library": {
+                "path": workspace.library_path(),
+                "repos": library_repos,
+                "shared_with": shared_with,
+            },
+            "repos": repos.len(),
+            "status": counts,
+        });
+        match format {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&summary)?),
+            _ => println!("{}", summary),
+        }
+        return Ok(());
+    }
+
     println!("Workspace: {}", workspace.path);
     println!();
 
@@ -549,23 +1249,51 @@ fn show_workspace_summary(workspace: &Workspace) -> Result<()> {
     if let Ok(repos) = workspace.list_library() {
         println!("  {} repository(ies) in library", repos.len());
     }
+    if let Ok(index) = workset::library::LibraryIndex::load(Path::new(&workspace.library_path()))
+        && index.workspaces.len() > 1
+    {
+        println!(
+            "  shared with {} other workspace(s)",
+            index.workspaces.len() - 1
+        );
+    }
 
     // Count repositories in workspace
     println!();
-    if let Ok(repos) = workset::find_git_repositories(Path::new(&workspace.path)) {
+    if let Ok(repos) = scan_workspace_sorted(Path::new(&workspace.path), freshness, show_progress) {
         println!("Active repositories: {}", repos.len());
 
         let mut clean = 0;
         let mut modified = 0;
         let mut unpushed = 0;
-
-        for repo in &repos {
-            match workset::check_repo_status(repo) {
-                Ok(workset::RepoStatus::Clean) => clean += 1,
-                Ok(workset::RepoStatus::Dirty) => modified += 1,
-                Ok(workset::RepoStatus::Unpushed) => unpushed += 1,
-                Ok(workset::RepoStatus::NoCommits) => modified += 1,
-                Err(_) => {}
+        let mut behind = 0;
+        let mut stashed = 0;
+        let mut in_progress = Vec::new();
+        let mut oldest_cached: Option<std::time::SystemTime> = None;
+
+        for scan in &repos {
+            let Ok(Some(cached)) = &scan.state else {
+                continue;
+            };
+            if cached.cached {
+                oldest_cached =
+                    Some(oldest_cached.map_or(cached.scanned_at, |t| t.min(cached.scanned_at)));
+            }
+            let state = &cached.state;
+            match state.status() {
+                workset::RepoStatus::Clean => clean += 1,
+                workset::RepoStatus::Dirty => modified += 1,
+                workset::RepoStatus::Unpushed => unpushed += 1,
+                workset::RepoStatus::NoCommits => modified += 1,
+            }
+            if state.upstream.as_ref().is_some_and(|u| u.behind > 0) {
+                behind += 1;
+            }
+            if state.stashes > 0 {
+                stashed += 1;
+            }
+            if state.operation.is_some() {
+                in_progress.push(scan.repo.clone());
             }
         }
 
@@ -578,6 +1306,25 @@ fn show_workspace_summary(workspace: &Workspace) -> Result<()> {
         if unpushed > 0 {
             println!("  ⚠ {} with unpushed commits", unpushed);
         }
+        if behind > 0 {
+            println!("  ↓ {} behind upstream", behind);
+        }
+        if stashed > 0 {
+            println!("  ≡ {} with stashes", stashed);
+        }
+        if !in_progress.is_empty() {
+            println!(
+                "  ⚠ {} with an unfinished merge, rebase or similar: {}",
+                in_progress.len(),
+                in_progress.join(", ")
+            );
+        }
+        if let Some(oldest) = oldest_cached {
+            println!(
+                "  (cached results up to {} old; --refresh to rescan)",
+                workset::format_time_ago(oldest)
+            );
+        }
     }
 
     Ok(())
@@ -601,58 +1348,57 @@ fn get_repo_completions(workspace: &Workspace) -> Vec<String> {
     repos
 }
 
-/// Get repository completions with metadata (status and modification time) for fish shell
+/// Get repository completions with metadata (status and modification time)
+/// for fish shell. Statuses come from the cache however old, so completing
+/// stays instant in large workspaces; old ones say when they were checked.
 fn get_repo_completions_with_metadata(workspace: &Workspace) -> Vec<(String, String)> {
     let mut repos = Vec::new();
+    let ttl = workspace.config.status.cache_ttl();
 
     // Only complete with local workspace repos
-    if let Ok(local_repos) = workset::find_git_repositories(Path::new(&workspace.path)) {
-        for repo in local_repos {
-            if let Ok(relative) = repo.strip_prefix(&workspace.path) {
-                let repo_name = relative.display().to_string();
-
-                // Get repo status and modification time in a single repo open
-                // If this fails, we'll still provide a basic completion
-                let (status, mod_time) =
-                    match workset::check_repo_status_and_modification_time(&repo) {
-                        Ok((status, mod_time)) => (Some(status), mod_time),
-                        Err(_) => (None, None),
-                    };
-
-                // Build description with status and time
-                let mut desc_parts = Vec::new();
-
-                // Add status indicator
-                match status {
-                    Some(workset::RepoStatus::Clean) => desc_parts.push("clean".to_string()),
-                    Some(workset::RepoStatus::Dirty) => desc_parts.push("dirty".to_string()),
-                    Some(workset::RepoStatus::Unpushed) => desc_parts.push("unpushed".to_string()),
-                    Some(workset::RepoStatus::NoCommits) => {
-                        desc_parts.push("no commits".to_string())
+    if let Ok(states) = scan_workspace_sorted(Path::new(&workspace.path), Freshness::Any, |_| {}) {
+        for scan in states {
+            // If the status check failed, we'll still provide a basic completion
+            let state = scan.state.ok().flatten();
+
+            // Build description with status and time
+            let mut desc_parts = Vec::new();
+
+            if let Some(cached) = &state {
+                desc_parts.push(
+                    match cached.state.status() {
+                        workset::RepoStatus::Clean => "clean",
+                        workset::RepoStatus::Dirty => "dirty",
+                        workset::RepoStatus::Unpushed => "unpushed",
+                        workset::RepoStatus::NoCommits => "no commits",
                     }
-                    None => {} // Don't add "unknown" if status check failed
-                }
+                    .to_string(),
+                );
 
                 // Add modification time
-                if let Some(time) = mod_time {
+                if let Some(time) = cached.modified {
                     desc_parts.push(workset::format_time_ago(time));
                 }
 
-                // If we couldn't get any metadata, use a default description
-                let description = if desc_parts.is_empty() {
-                    "repository".to_string()
-                } else {
-                    desc_parts.join(", ")
-                };
-
-                repos.push((repo_name, description));
+                if cached.age() > ttl {
+                    desc_parts.push(format!(
+                        "checked {} ago",
+                        workset::format_time_ago(cached.scanned_at)
+                    ));
+                }
             }
+
+            // If we couldn't get any metadata, use a default description
+            let description = if desc_parts.is_empty() {
+                "repository".to_string()
+            } else {
+                desc_parts.join(", ")
+            };
+
+            repos.push((scan.repo, description));
         }
     }
 
-    // Sort by repo name
-    repos.sort_by(|a, b| a.0.cmp(&b.0));
-    repos.dedup();
     repos
 }
 
@@ -688,7 +1434,9 @@ fn complete_bash(maybe_workspace: Option<Workspace>) -> Result<()> {
     if word_index <= 1 {
         // Complete subcommands
         let subcommands: &[&str] = if maybe_workspace.is_some() {
-            &["clone", "restore", "drop", "list", "ls", "status"]
+            &[
+                "clone", "restore", "drop", "apply", "prune", "list", "ls", "status", "du", "sync",
+            ]
         } else {
             &["init"]
         };
@@ -701,14 +1449,17 @@ fn complete_bash(maybe_workspace: Option<Workspace>) -> Result<()> {
         // Complete repository paths based on the subcommand
         let subcommand = words.get(1).unwrap_or(&"");
         if *subcommand == "restore" {
-            // For restore, complete from library
-            if let Ok(library_repos) = workspace.list_library() {
+            // For restore, complete from library, most recently dropped first
+            if let Ok(library_repos) = workspace.list_library_by_recency() {
                 for repo in library_repos {
                     if repo.starts_with(current_word) {
                         println!("{}", repo);
                     }
                 }
             }
+        } else if *subcommand == "apply" {
+            // Apply takes a manifest file, which bash completes itself when
+            // there's nothing to offer
         } else {
             // For drop and other commands, complete from workspace
             for repo in get_repo_completions(&workspace) {
@@ -734,9 +1485,13 @@ fn complete_fish(maybe_workspace: Option<Workspace>) -> Result<()> {
             println!("clone\tClone new repository(ies) to workspace");
             println!("restore\tRestore repository(ies) from library");
             println!("drop\tDrop one or more repositories");
+            println!("apply\tClone or restore the repos a manifest lists");
+            println!("prune\tDrop clean repositories untouched for a while");
             println!("list\tList all repositories with their status");
             println!("ls\tList all repositories with their status");
             println!("status\tShow workspace summary and statistics");
+            println!("du\tShow how much disk space each repo takes");
+            println!("sync\tMirror pushed commits to all of each repo's remotes");
         } else {
             println!("init\tInitialize a workspace in current directory");
         }
@@ -744,12 +1499,14 @@ fn complete_fish(maybe_workspace: Option<Workspace>) -> Result<()> {
         // Complete repository paths based on the subcommand
         let subcommand = words.get(1).unwrap_or(&"");
         if *subcommand == "restore" {
-            // For restore, complete from library
-            if let Ok(library_repos) = workspace.list_library() {
+            // For restore, complete from library, most recently dropped first
+            if let Ok(library_repos) = workspace.list_library_by_recency() {
                 for repo in library_repos {
                     println!("{}\tlibrary", repo);
                 }
             }
+        } else if *subcommand == "apply" {
+            // Apply takes a manifest file, which the fish completions offer
         } else {
             // For drop and other commands, complete from workspace with metadata
             for (repo_name, description) in get_repo_completions_with_metadata(&workspace) {
@@ -0,0 +1,293 @@
+//! Declarative working sets.
+//!
+//! A manifest lists the repos a project needs so a team can check it in and
+//! reproduce the same workspace anywhere:
+//!
+//! ```toml
+//! [[repo]]
+//! pattern = "github.com/fossable/workset"
+//! branch = "main"
+//! remotes = { upstream = "https://github.com/upstream/workset" }
+//! ```
+//!
+//! [`Manifest::plan`] compares the manifest against the workspace and its
+//! library and produces the steps needed to bring the workspace in line; the
+//! caller decides whether to print them or carry them out.
+
+use crate::{
+    RepoPattern, RepoStatus, Workspace, check_repo_status, find_git_repositories, remote_urls,
+};
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default)]
+    pub repo: Vec<ManifestRepo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestRepo {
+    /// Workspace path of the repo (e.g. "github.com/user/repo") or its URL
+    pub pattern: String,
+    /// Remotes the repo should have, by name. An `origin` entry is also used
+    /// as the clone URL.
+    #[serde(default)]
+    pub remotes: BTreeMap<String, String>,
+    /// Branch to check out after the repo is cloned or restored
+    pub branch: Option<String>,
+}
+
+/// One change needed to bring the workspace in line with a manifest. Paths
+/// are relative to the workspace root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// Clone a repo that's in neither the workspace nor the library
+    Clone { path: String, url: String },
+    /// Restore a repo from the library
+    Restore { path: String },
+    /// Add a missing remote, or point an existing one at a new URL
+    Remote {
+        path: String,
+        name: String,
+        url: String,
+        exists: bool,
+    },
+    /// Check out a branch in a repo that was just cloned or restored
+    Checkout { path: String, branch: String },
+    /// Drop a clean repo that isn't in the manifest
+    Drop { path: String },
+    /// A repo that isn't in the manifest but can't be dropped safely
+    Keep { path: String, status: RepoStatus },
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Clone { path, url } => write!(f, "clone    {} from {}", path, url),
+            Step::Restore { path } => write!(f, "restore  {}", path),
+            Step::Remote {
+                path,
+                name,
+                url,
+                exists,
+            } => {
+                let verb = if *exists { "set" } else { "add" };
+                write!(f, "remote   {}: {} {} {}", path, verb, name, url)
+            }
+            Step::Checkout { path, branch } => write!(f, "checkout {}: {}", path, branch),
+            Step::Drop { path } => write!(f, "drop     {}", path),
+            Step::Keep { path, status } => {
+                let reason = match status {
+                    RepoStatus::Dirty => "uncommitted changes",
+                    RepoStatus::Unpushed => "unpushed commits",
+                    RepoStatus::NoCommits => "no commits",
+                    RepoStatus::Clean => "clean",
+                };
+                write!(f, "keep     {} ({})", path, reason)
+            }
+        }
+    }
+}
+
+impl Manifest {
+    /// Read a manifest file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid manifest {}", path.display()))
+    }
+
+    /// Parse and validate manifest contents. Every entry must name exactly
+    /// one repo, including its provider.
+    pub fn parse(content: &str) -> Result<Self> {
+        let manifest: Self = toml::from_str(content)?;
+        for entry in &manifest.repo {
+            let pattern = entry.repo_pattern();
+            if pattern.provider.is_none() || pattern.is_glob() || pattern.is_relative_path() {
+                bail!(
+                    "'{}' must be a full repo path like github.com/user/repo",
+                    entry.pattern
+                );
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Work out the steps that bring the workspace in line with the
+    /// manifest. With `prune`, repos that aren't in the manifest are dropped
+    /// when they're clean.
+    pub fn plan(&self, workspace: &Workspace, prune: bool) -> Result<Vec<Step>> {
+        let root = Path::new(&workspace.path);
+        let library = workspace.library_path_buf();
+        let mut steps = Vec::new();
+
+        for entry in &self.repo {
+            let pattern = entry.repo_pattern();
+            let path = pattern.full_path();
+            let (existing_remotes, obtained) = if root.join(&path).exists() {
+                (remote_urls(&root.join(&path)), false)
+            } else if workspace.library_contains(&path) {
+                steps.push(Step::Restore { path: path.clone() });
+                (remote_urls(&library.join(&path)), true)
+            } else {
+                let Some(url) = pattern.clone_url(&workspace.config) else {
+                    bail!("Can't work out where to clone {} from", path);
+                };
+                steps.push(Step::Clone {
+                    path: path.clone(),
+                    url: url.clone(),
+                });
+                (BTreeMap::from([("origin".to_string(), url)]), true)
+            };
+
+            for (name, url) in &entry.remotes {
+                let existing = existing_remotes.get(name);
+                if existing != Some(url) {
+                    steps.push(Step::Remote {
+                        path: path.clone(),
+                        name: name.clone(),
+                        url: url.clone(),
+                        exists: existing.is_some(),
+                    });
+                }
+            }
+            if obtained && let Some(branch) = &entry.branch {
+                steps.push(Step::Checkout {
+                    path: path.clone(),
+                    branch: branch.clone(),
+                });
+            }
+        }
+
+        if prune {
+            let wanted: Vec<String> = self
+                .repo
+                .iter()
+                .map(|entry| entry.repo_pattern().full_path())
+                .collect();
+            let mut repos = find_git_repositories(root)?;
+            repos.sort();
+            for repo in repos {
+                let path = repo
+                    .strip_prefix(root)
+                    .unwrap_or(&repo)
+                    .display()
+                    .to_string();
+                if wanted.contains(&path) {
+                    continue;
+                }
+                match check_repo_status(&repo)? {
+                    RepoStatus::Clean => steps.push(Step::Drop { path }),
+                    status => steps.push(Step::Keep { path, status }),
+                }
+            }
+        }
+
+        Ok(steps)
+    }
+}
+
+impl ManifestRepo {
+    /// The entry's pattern, cloning from its `origin` remote if it has one
+    pub fn repo_pattern(&self) -> RepoPattern {
+        let Ok(mut pattern) = self.pattern.parse::<RepoPattern>();
+        if let Some(origin) = self.remotes.get("origin") {
+            pattern.url = Some(origin.clone());
+        }
+        pattern
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::git;
+    use tempfile::TempDir;
+
+    fn commit_repo(path: &Path) {
+        std::fs::create_dir_all(path).unwrap();
+        git(path, &["init", "-q", "-b", "main"]);
+        git(path, &["commit", "-q", "--allow-empty", "-m", "initial"]);
+        // As if it was pushed, so it's clean
+        git(path, &["update-ref", "refs/remotes/origin/main", "HEAD"]);
+    }
+
+    #[test]
+    fn rejects_entries_that_dont_name_one_repo() {
+        for pattern in ["user/repo", "github.com/user/*", "./repo"] {
+            let manifest = format!("[[repo]]\npattern = \"{}\"\n", pattern);
+            assert!(Manifest::parse(&manifest).is_err(), "{pattern}");
+        }
+        assert!(
+            Manifest::parse("[[repo]]\npattern = \"github.com/a/b\"\nbrnach = \"x\"\n").is_err()
+        );
+    }
+
+    #[test]
+    fn plan_covers_clone_restore_remotes_and_prune() {
+        let temp = TempDir::new().unwrap();
+        let workspace = Workspace {
+            path: temp.path().display().to_string(),
+            config: crate::config::Config::default(),
+        };
+        commit_repo(&temp.path().join("github.com/a/present"));
+        commit_repo(&temp.path().join("github.com/a/extra"));
+        commit_repo(&temp.path().join("github.com/a/dirty"));
+        std::fs::write(temp.path().join("github.com/a/dirty/new.txt"), "").unwrap();
+        std::fs::create_dir_all(workspace.library_path_buf().join("github.com/a/stored")).unwrap();
+
+        let manifest = Manifest::parse(
+            r#"
+            [[repo]]
+            pattern = "github.com/a/present"
+            branch = "dev"
+            remotes = { upstream = "https://example.com/present" }
+
+            [[repo]]
+            pattern = "github.com/a/stored"
+
+            [[repo]]
+            pattern = "https://github.com/a/new.git"
+            branch = "dev"
+            "#,
+        )
+        .unwrap();
+
+        let steps = manifest.plan(&workspace, true).unwrap();
+        assert_eq!(
+            steps,
+            vec![
+                Step::Remote {
+                    path: "github.com/a/present".into(),
+                    name: "upstream".into(),
+                    url: "https://example.com/present".into(),
+                    exists: false,
+                },
+                Step::Restore {
+                    path: "github.com/a/stored".into()
+                },
+                Step::Clone {
+                    path: "github.com/a/new".into(),
+                    url: "https://github.com/a/new.git".into(),
+                },
+                Step::Checkout {
+                    path: "github.com/a/new".into(),
+                    branch: "dev".into(),
+                },
+                Step::Keep {
+                    path: "github.com/a/dirty".into(),
+                    status: RepoStatus::Dirty,
+                },
+                Step::Drop {
+                    path: "github.com/a/extra".into()
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Finding repos that have gone stale.
+//!
+//! A repo is stale when it's clean and nothing has happened in it for a
+//! while: no commit, no change to its files, and no clone, checkout or
+//! restore, which show in its index and top-level directory. An old last
+//! commit alone doesn't make a repo stale, since a repo that was just cloned
+//! to read or build it has one too. `workset prune` drops stale
+//! repos in one go so the working set stays small. Repos matching one of the
+//! `prune.pinned` patterns from the config are never considered.
+
+use crate::scan::{Progress, default_workers, for_each_parallel};
+use crate::{
+    RepoPattern, RepoStatus, Workspace, check_repo_status_and_modification_time,
+    find_git_repositories,
+};
+use anyhow::{Result, bail};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, SystemTime};
+
+/// A clean repo that hasn't been touched in a while
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleRepo {
+    /// Path relative to the workspace root
+    pub repo: String,
+    pub path: PathBuf,
+    /// When the repo was last used: its last commit, the latest change to a
+    /// file, or the last clone, checkout or restore, whichever is newest
+    pub modified: SystemTime,
+}
+
+/// Parse an age like `30d`: a whole number followed by `s`, `m`, `h`, `d`,
+/// `w`, `mo` (30 days) or `y` (365 days), the same units `list` shows
+pub fn parse_age(age: &str) -> Result<Duration> {
+    let age = age.trim();
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let (number, unit) = age.split_at(split);
+    let Ok(number) = number.parse::<u64>() else {
+        bail!("invalid age '{}', expected something like 30d", age);
+    };
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 604_800,
+        "mo" => 2_592_000,
+        "y" => 31_536_000,
+        _ => bail!(
+            "invalid age '{}', the unit must be one of s, m, h, d, w, mo or y",
+            age
+        ),
+    };
+    Ok(Duration::from_secs(number.saturating_mul(seconds)))
+}
+
+/// Whether a workspace-relative repo path matches one of the pinned patterns
+pub fn is_pinned(repo: &str, pinned: &[String]) -> bool {
+    pinned.iter().any(|pattern| {
+        let Ok(pattern) = pattern.parse::<RepoPattern>();
+        pattern.matches(repo)
+    })
+}
+
+/// Find the clean, unpinned repos under `path` that haven't been modified
+/// for at least `older_than`, least recently modified first. Repos are
+/// checked in parallel and always scanned afresh, since the result decides
+/// what gets dropped.
+pub fn find_stale(
+    workspace: &Workspace,
+    path: &Path,
+    older_than: Duration,
+    mut on_progress: impl FnMut(Progress),
+) -> Result<Vec<StaleRepo>> {
+    let root = Path::new(&workspace.path);
+    let cutoff = SystemTime::now()
+        .checked_sub(older_than)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let candidates: Vec<(String, PathBuf)> = find_git_repositories(path)?
+        .into_iter()
+        .map(|path| {
+            let repo = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+            (repo, path)
+        })
+        .filter(|(repo, _)| !is_pinned(repo, &workspace.config.prune.pinned))
+        .collect();
+
+    let mut stale = Vec::new();
+    for_each_parallel(
+        candidates,
+        default_workers(),
+        &AtomicBool::new(false),
+        |(repo, path)| match check_repo_status_and_modification_time(&path) {
+            Ok((RepoStatus::Clean, Some(modified))) => {
+                let modified = last_used(&path, modified);
+                (modified <= cutoff).then_some(StaleRepo {
+                    repo,
+                    path,
+                    modified,
+                })
+            }
+            _ => None,
+        },
+        |result, progress| {
+            stale.extend(result);
+            on_progress(progress);
+        },
+    );
+    stale.sort_by(|a, b| {
+        a.modified
+            .cmp(&b.modified)
+            .then_with(|| a.repo.cmp(&b.repo))
+    });
+    Ok(stale)
+}
+
+/// The later of `modified` (the last commit, or the latest change to a
+/// dirty file) and the last change to the repo's index or top-level
+/// directory. Cloning, checking out and staging write the index, and
+/// restoring puts the git directory back in place.
+fn last_used(path: &Path, modified: SystemTime) -> SystemTime {
+    [crate::git_dir(path).join("index"), path.to_path_buf()]
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .fold(modified, SystemTime::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::git_with_env;
+    use tempfile::TempDir;
+
+    #[test]
+    fn ages_parse_with_units() {
+        assert_eq!(parse_age("30d").unwrap(), Duration::from_secs(30 * 86_400));
+        assert_eq!(parse_age("2w").unwrap(), Duration::from_secs(14 * 86_400));
+        assert_eq!(
+            parse_age("6mo").unwrap(),
+            Duration::from_secs(6 * 2_592_000)
+        );
+        assert_eq!(parse_age("90s").unwrap(), Duration::from_secs(90));
+        for invalid in ["", "d", "30", "30x", "-1d", "1.5d"] {
+            assert!(parse_age(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn fresh_clones_of_old_repos_are_not_stale() {
+        let temp = TempDir::new().unwrap();
+        let workspace = Workspace {
+            path: temp.path().display().to_string(),
+            config: crate::config::Config::default(),
+        };
+        let repo = temp.path().join("old");
+        std::fs::create_dir_all(&repo).unwrap();
+        let git = |args: &[&str]| {
+            let date = "2020-01-01T00:00:00Z";
+            git_with_env(
+                &repo,
+                args,
+                &[("GIT_AUTHOR_DATE", date), ("GIT_COMMITTER_DATE", date)],
+            );
+        };
+        git(&["init", "-q", "-b", "main"]);
+        std::fs::write(repo.join("file"), "content").unwrap();
+        git(&["add", "file"]);
+        git(&["commit", "-q", "-m", "old"]);
+        git(&["update-ref", "refs/remotes/origin/main", "HEAD"]);
+        let month = Duration::from_secs(30 * 86_400);
+        assert_eq!(
+            find_stale(&workspace, temp.path(), month, |_| {}).unwrap(),
+            []
+        );
+
+        let long_ago = SystemTime::UNIX_EPOCH + Duration::from_secs(1_577_836_800);
+        for path in [repo.join(".git/index"), repo.clone()] {
+            std::fs::File::open(path)
+                .unwrap()
+                .set_modified(long_ago)
+                .unwrap();
+        }
+        let stale = find_stale(&workspace, temp.path(), month, |_| {}).unwrap();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].modified, long_ago);
+    }
+
+    #[test]
+    fn pinned_patterns_match_like_repo_patterns() {
+        let pinned = vec!["github.com/fossable/*".to_string(), "dotfiles".to_string()];
+        assert!(is_pinned("github.com/fossable/workset", &pinned));
+        assert!(is_pinned("gitlab.com/me/dotfiles", &pinned));
+        assert!(!is_pinned("github.com/other/workset", &pinned));
+        assert!(!is_pinned("github.com/me/dotfiles-old", &pinned));
+    }
+}
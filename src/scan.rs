@@ -0,0 +1,224 @@
+//! Working on many repos at once.
+//!
+//! [`for_each_parallel`] runs a job per item on a pool of worker threads and
+//! streams each result back to the calling thread as soon as it's ready,
+//! along with progress, until the items run out or the caller cancels.
+//! [`scan_workspace`] uses it to read the state of every repo in a workspace
+//! through the status cache. `list`, `status`, `sync`, completions and the TUI
+//! are all built on these.
+
+use crate::cache::{self, CachedState, Freshness, StatusCache};
+use crate::{find_git_repositories, worktree_main};
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, mpsc};
+use tracing::debug;
+
+/// How far a parallel run has got
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Items finished so far, including the one just delivered
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Worker threads to use for work that's mostly disk bound: one per core,
+/// up to 8
+pub fn default_workers() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .min(8)
+}
+
+/// Run `work` on every item using up to `workers` threads, handing each
+/// result to `on_result` on the calling thread in the order they finish.
+/// Items are started in order. Once `cancel` is set no more items are
+/// started, though the results of running ones are still delivered; setting
+/// it from `on_result` stops the run early.
+pub fn for_each_parallel<T, R>(
+    items: impl IntoIterator<Item = T>,
+    workers: usize,
+    cancel: &AtomicBool,
+    work: impl Fn(T) -> R + Sync,
+    mut on_result: impl FnMut(R, Progress),
+) where
+    T: Send,
+    R: Send,
+{
+    let queue: Mutex<VecDeque<T>> = Mutex::new(items.into_iter().collect());
+    let total = queue.lock().unwrap().len();
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, total.max(1)) {
+            let tx = tx.clone();
+            let queue = &queue;
+            let work = &work;
+            scope.spawn(move || {
+                while !cancel.load(Ordering::Relaxed) {
+                    let item = queue.lock().unwrap().pop_front();
+                    let Some(item) = item else {
+                        break;
+                    };
+                    if tx.send(work(item)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        // The workers hold the remaining senders, so the loop ends once
+        // they've all finished
+        drop(tx);
+
+        for (done, result) in (1..).zip(rx) {
+            on_result(result, Progress { done, total });
+        }
+    });
+}
+
+/// One repo's result from [`scan_workspace`]
+#[derive(Debug)]
+pub struct RepoScan {
+    /// Path relative to the workspace root
+    pub repo: String,
+    pub path: PathBuf,
+    /// For a linked worktree, the main repo it was added from, relative to
+    /// the workspace root when it's inside the workspace
+    pub worktree_of: Option<String>,
+    /// The repo's state, or `None` if it couldn't be opened
+    pub state: Result<Option<CachedState>>,
+}
+
+impl RepoScan {
+    /// Orders linked worktrees right after the repo they belong to
+    fn sort_key(&self) -> (&str, bool, &str) {
+        let group = self.worktree_of.as_deref().unwrap_or(&self.repo);
+        (group, self.worktree_of.is_some(), &self.repo)
+    }
+}
+
+/// Read the state of every repo in the workspace in parallel, serving what
+/// the freshness allows from the status cache, and hand each one to
+/// `on_repo` as it's ready. The cache is saved afterwards, without the
+/// entries of repos that no longer exist.
+pub fn scan_workspace(
+    workspace_root: &Path,
+    freshness: Freshness,
+    cancel: &AtomicBool,
+    mut on_repo: impl FnMut(RepoScan, Progress),
+) -> Result<()> {
+    let repos: Vec<(String, PathBuf)> = find_git_repositories(workspace_root)?
+        .into_iter()
+        .map(|path| {
+            let repo = path
+                .strip_prefix(workspace_root)
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+            (repo, path)
+        })
+        .collect();
+    let names: Vec<String> = repos.iter().map(|(repo, _)| repo.clone()).collect();
+    let cache = Mutex::new(StatusCache::load(workspace_root));
+
+    for_each_parallel(
+        repos,
+        default_workers(),
+        cancel,
+        |(repo, path)| {
+            let state = cache::lookup(&cache, &repo, &path, freshness);
+            let worktree_of = worktree_main(&path).map(|main| {
+                main.strip_prefix(workspace_root)
+                    .unwrap_or(&main)
+                    .display()
+                    .to_string()
+            });
+            RepoScan {
+                repo,
+                path,
+                worktree_of,
+                state,
+            }
+        },
+        &mut on_repo,
+    );
+
+    let mut cache = cache.into_inner().unwrap_or_else(|e| e.into_inner());
+    cache.retain(&names);
+    if let Err(e) = cache.save(workspace_root) {
+        debug!(error = %e, "Failed to save status cache");
+    }
+    Ok(())
+}
+
+/// [`scan_workspace`] collected into a list sorted by repo path, with linked
+/// worktrees following their main repo, reporting progress along the way
+pub fn scan_workspace_sorted(
+    workspace_root: &Path,
+    freshness: Freshness,
+    mut on_progress: impl FnMut(Progress),
+) -> Result<Vec<RepoScan>> {
+    let mut scans = Vec::new();
+    scan_workspace(
+        workspace_root,
+        freshness,
+        &AtomicBool::new(false),
+        |scan, progress| {
+            scans.push(scan);
+            on_progress(progress);
+        },
+    )?;
+    scans.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+    Ok(scans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_stream_with_progress_until_cancelled() {
+        let cancel = AtomicBool::new(false);
+        let mut results = Vec::new();
+        let mut last = None;
+        for_each_parallel(
+            1..=20,
+            4,
+            &cancel,
+            |n| n * 2,
+            |result, progress| {
+                results.push(result);
+                last = Some(progress);
+            },
+        );
+        results.sort();
+        assert_eq!(results, (1..=20).map(|n| n * 2).collect::<Vec<_>>());
+        assert_eq!(
+            last,
+            Some(Progress {
+                done: 20,
+                total: 20
+            })
+        );
+
+        // Cancelling from the callback stops new items from starting
+        let mut delivered = 0;
+        for_each_parallel(
+            0..1000,
+            2,
+            &cancel,
+            |n| {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+                n
+            },
+            |_, _| {
+                delivered += 1;
+                cancel.store(true, Ordering::Relaxed);
+            },
+        );
+        assert!(delivered < 1000);
+    }
+}
@@ -0,0 +1,451 @@
+//! Detailed repository state.
+//!
+//! [`RepoState`] is everything `list`, `status` and the TUI show about a repo:
+//! what HEAD points at, how many files are staged, modified or untracked, how
+//! far the branch is from its upstream, stashes, and any merge or rebase that
+//! was left unfinished. [`RepoStatus`] is the one-word summary derived from it
+//! that drop safety and the status icons are based on.
+
+use crate::{RepoStatus, unpushed_refs_with_handle};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::SystemTime;
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RepoState {
+    pub head: HeadState,
+    /// Files whose staged content differs from HEAD
+    pub staged: usize,
+    /// Tracked files whose worktree content differs from the index
+    pub modified: usize,
+    /// Files unknown to git and not ignored
+    pub untracked: usize,
+    /// Files with unresolved merge conflicts
+    pub conflicted: usize,
+    /// How far the checked-out branch is from its upstream, if it has one
+    pub upstream: Option<Upstream>,
+    pub stashes: usize,
+    /// A merge, rebase or similar that was started and not finished
+    pub operation: Option<Operation>,
+    /// Local branches and tags (and a detached HEAD) with commits that no
+    /// remote-tracking ref contains
+    pub unpushed: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HeadState {
+    /// On a branch that has no commits yet
+    Unborn {
+        branch: String,
+    },
+    Branch {
+        branch: String,
+    },
+    /// Not on any branch; the commit is abbreviated
+    Detached {
+        commit: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Upstream {
+    /// Remote-tracking branch, e.g. "origin/main"
+    pub name: String,
+    /// Commits on the branch that aren't upstream
+    pub ahead: usize,
+    /// Commits upstream that aren't on the branch
+    pub behind: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Merge,
+    Rebase,
+    CherryPick,
+    Revert,
+    Bisect,
+    ApplyMailbox,
+}
+
+impl Operation {
+    fn name(self) -> &'static str {
+        match self {
+            Operation::Merge => "merge",
+            Operation::Rebase => "rebase",
+            Operation::CherryPick => "cherry-pick",
+            Operation::Revert => "revert",
+            Operation::Bisect => "bisect",
+            Operation::ApplyMailbox => "am",
+        }
+    }
+}
+
+impl RepoState {
+    /// Read the state of the repo at the given path
+    pub fn load(repo_path: &Path) -> Result<Self> {
+        let repo = gix::open(repo_path)?;
+        Ok(scan(&repo, repo_path).0)
+    }
+
+    /// The one-word summary of the state
+    pub fn status(&self) -> RepoStatus {
+        if matches!(self.head, HeadState::Unborn { .. }) {
+            RepoStatus::NoCommits
+        } else if self.staged + self.modified + self.untracked + self.conflicted > 0 {
+            RepoStatus::Dirty
+        } else if !self.unpushed.is_empty() {
+            RepoStatus::Unpushed
+        } else {
+            RepoStatus::Clean
+        }
+    }
+
+    /// Human-readable notes on everything that isn't as expected, e.g.
+    /// "2 staged" or "rebase in progress"; empty for a repo that's simply
+    /// clean and in sync
+    pub fn details(&self) -> Vec<String> {
+        let mut details = Vec::new();
+        if let Some(operation) = self.operation {
+            details.push(format!("{} in progress", operation.name()));
+        }
+        if let HeadState::Detached { commit } = &self.head {
+            details.push(format!("detached at {}", commit));
+        }
+        for (count, what) in [
+            (self.conflicted, "conflicted"),
+            (self.staged, "staged"),
+            (self.modified, "modified"),
+            (self.untracked, "untracked"),
+        ] {
+            if count > 0 {
+                details.push(format!("{} {}", count, what));
+            }
+        }
+        if let Some(upstream) = &self.upstream {
+            if upstream.ahead > 0 {
+                details.push(format!("{} ahead", upstream.ahead));
+            }
+            if upstream.behind > 0 {
+                details.push(format!("{} behind", upstream.behind));
+            }
+        }
+        match self.stashes {
+            0 => {}
+            1 => details.push("1 stash".to_string()),
+            n => details.push(format!("{} stashes", n)),
+        }
+        details
+    }
+
+    /// A few characters for tight spaces like the TUI: ahead/behind arrows,
+    /// stashes and the operation in progress
+    pub fn compact(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(operation) = self.operation {
+            parts.push(operation.name().to_string());
+        }
+        if let Some(upstream) = &self.upstream {
+            if upstream.ahead > 0 {
+                parts.push(format!("↑{}", upstream.ahead));
+            }
+            if upstream.behind > 0 {
+                parts.push(format!("↓{}", upstream.behind));
+            }
+        }
+        if self.stashes > 0 {
+            parts.push(format!("≡{}", self.stashes));
+        }
+        parts.join(" ")
+    }
+}
+
+/// Work out the state of an open repo with a single worktree scan. Also
+/// returns the latest modification time among changed files, which the TUI
+/// uses to sort dirty repos.
+pub(crate) fn scan(repo: &gix::Repository, repo_path: &Path) -> (RepoState, SystemTime) {
+    let head = head_state(repo);
+    let mut state = RepoState {
+        upstream: match head {
+            HeadState::Branch { .. } => upstream(repo),
+            _ => None,
+        },
+        head,
+        staged: 0,
+        modified: 0,
+        untracked: 0,
+        conflicted: 0,
+        stashes: stash_count(repo),
+        operation: repo.state().map(|op| {
+            use gix::state::InProgress;
+            match op {
+                InProgress::Merge => Operation::Merge,
+                InProgress::Rebase | InProgress::RebaseInteractive => Operation::Rebase,
+                InProgress::CherryPick | InProgress::CherryPickSequence => Operation::CherryPick,
+                InProgress::Revert | InProgress::RevertSequence => Operation::Revert,
+                InProgress::Bisect => Operation::Bisect,
+                InProgress::ApplyMailbox | InProgress::ApplyMailboxRebase => {
+                    Operation::ApplyMailbox
+                }
+            }
+        }),
+        unpushed: Vec::new(),
+    };
+    let latest_change = count_changes(repo, repo_path, &mut state);
+    if state.status() != RepoStatus::NoCommits {
+        state.unpushed = unpushed_refs_with_handle(repo);
+    }
+    (state, latest_change)
+}
+
+fn head_state(repo: &gix::Repository) -> HeadState {
+    let head = match repo.head() {
+        Ok(head) => head,
+        Err(e) => {
+            debug!(error = %e, "Failed to read HEAD");
+            return HeadState::Unborn {
+                branch: String::new(),
+            };
+        }
+    };
+    match head.kind {
+        gix::head::Kind::Unborn(name) => HeadState::Unborn {
+            branch: name.shorten().to_string(),
+        },
+        gix::head::Kind::Symbolic(reference) => HeadState::Branch {
+            branch: reference.name.shorten().to_string(),
+        },
+        gix::head::Kind::Detached { target, .. } => HeadState::Detached {
+            commit: repo
+                .find_object(target)
+                .map_err(anyhow::Error::from)
+                .and_then(|object| Ok(object.id().shorten()?))
+                .map_or_else(|_| target.to_string(), |prefix| prefix.to_string()),
+        },
+    }
+}
+
+/// The checked-out branch's upstream and how far apart the two are
+fn upstream(repo: &gix::Repository) -> Option<Upstream> {
+    let head = repo.head().ok()?;
+    let branch = head.referent_name()?;
+    let tracking = repo
+        .branch_remote_tracking_ref_name(branch, gix::remote::Direction::Fetch)?
+        .ok()?;
+    let upstream_id = repo
+        .find_reference(tracking.as_ref())
+        .ok()?
+        .peel_to_id()
+        .ok()?
+        .detach();
+    let head_id = repo.head_id().ok()?.detach();
+
+    let count = |tip: gix::ObjectId, hidden: gix::ObjectId| {
+        repo.rev_walk([tip])
+            .with_hidden([hidden])
+            .all()
+            .map(|walk| walk.filter(Result::is_ok).count())
+            .unwrap_or(0)
+    };
+    Some(Upstream {
+        name: tracking.shorten().to_string(),
+        ahead: count(head_id, upstream_id),
+        behind: count(upstream_id, head_id),
+    })
+}
+
+/// Number of entries in the stash
+fn stash_count(repo: &gix::Repository) -> usize {
+    let Ok(stash) = repo.find_reference("refs/stash") else {
+        return 0;
+    };
+    let mut log = stash.log_iter();
+    match log.all() {
+        Ok(Some(entries)) => entries.filter(Result::is_ok).count(),
+        _ => 0,
+    }
+}
+
+/// Count staged, modified, untracked and conflicted files, returning the
+/// latest modification time among the files changed in the worktree
+fn count_changes(repo: &gix::Repository, repo_path: &Path, state: &mut RepoState) -> SystemTime {
+    use gix::status::index_worktree::Item as WorktreeItem;
+    use gix::status::plumbing::index_as_worktree::EntryStatus;
+
+    let mut latest_time = SystemTime::UNIX_EPOCH;
+    let platform = match repo.status(gix::progress::Discard) {
+        Ok(platform) => platform,
+        Err(e) => {
+            warn!(path = %repo_path.display(), error = %e, "Failed to create status platform");
+            return latest_time;
+        }
+    };
+    let iter = match platform
+        .untracked_files(gix::status::UntrackedFiles::Files)
+        .into_iter(None)
+    {
+        Ok(iter) => iter,
+        Err(e) => {
+            warn!(path = %repo_path.display(), error = %e, "Failed to check for changes");
+            return latest_time;
+        }
+    };
+
+    for item in iter.flatten() {
+        let worktree_item = match item {
+            gix::status::Item::TreeIndex(_) => {
+                state.staged += 1;
+                continue;
+            }
+            gix::status::Item::IndexWorktree(item) => item,
+        };
+        match &worktree_item {
+            WorktreeItem::Modification { status, .. } => match status {
+                EntryStatus::Conflict { .. } => state.conflicted += 1,
+                EntryStatus::Change(_) | EntryStatus::IntentToAdd => state.modified += 1,
+                // Only the file's stat info is out of date
+                EntryStatus::NeedsUpdate(_) => continue,
+            },
+            WorktreeItem::DirectoryContents { entry, .. } => {
+                if entry.status != gix::dir::entry::Status::Untracked {
+                    continue;
+                }
+                state.untracked += 1;
+            }
+            WorktreeItem::Rewrite { .. } => state.modified += 1,
+        }
+        let file_path = repo_path.join(gix::path::from_bstr(worktree_item.rela_path()));
+        if let Ok(metadata) = std::fs::metadata(&file_path)
+            && let Ok(modified) = metadata.modified()
+            && modified > latest_time
+        {
+            latest_time = modified;
+        }
+    }
+    latest_time
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::git;
+    use tempfile::TempDir;
+
+    #[test]
+    fn counts_changes_stashes_and_upstream() {
+        let temp = TempDir::new().unwrap();
+        let remote = temp.path().join("remote.git");
+        let repo = temp.path().join("repo");
+        std::fs::create_dir_all(&repo).unwrap();
+        git(
+            temp.path(),
+            &["init", "-q", "--bare", "-b", "main", "remote.git"],
+        );
+        git(&repo, &["init", "-q", "-b", "main"]);
+        for file in ["a", "b", "c"] {
+            std::fs::write(repo.join(file), file).unwrap();
+        }
+        git(&repo, &["add", "."]);
+        git(&repo, &["commit", "-q", "-m", "initial"]);
+        git(
+            &repo,
+            &["remote", "add", "origin", remote.to_str().unwrap()],
+        );
+        git(&repo, &["push", "-q", "-u", "origin", "main"]);
+        git(&repo, &["commit", "-q", "--allow-empty", "-m", "local"]);
+
+        std::fs::write(repo.join("a"), "stashed").unwrap();
+        git(&repo, &["stash", "-q"]);
+        std::fs::write(repo.join("a"), "staged").unwrap();
+        git(&repo, &["add", "a"]);
+        std::fs::write(repo.join("b"), "modified").unwrap();
+        std::fs::write(repo.join("new1"), "").unwrap();
+        std::fs::write(repo.join("new2"), "").unwrap();
+
+        let state = RepoState::load(&repo).unwrap();
+        assert_eq!(
+            state.head,
+            HeadState::Branch {
+                branch: "main".into()
+            }
+        );
+        assert_eq!((state.staged, state.modified, state.untracked), (1, 1, 2));
+        assert_eq!(state.stashes, 1);
+        assert_eq!(
+            state.upstream,
+            Some(Upstream {
+                name: "origin/main".into(),
+                ahead: 1,
+                behind: 0
+            })
+        );
+        assert_eq!(state.operation, None);
+        assert_eq!(state.status(), RepoStatus::Dirty);
+        assert_eq!(
+            state.details(),
+            [
+                "1 staged",
+                "1 modified",
+                "2 untracked",
+                "1 ahead",
+                "1 stash"
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_unborn_detached_and_in_progress() {
+        let temp = TempDir::new().unwrap();
+        git(temp.path(), &["init", "-q", "-b", "main"]);
+        let state = RepoState::load(temp.path()).unwrap();
+        assert_eq!(state.status(), RepoStatus::NoCommits);
+
+        git(temp.path(), &["commit", "-q", "--allow-empty", "-m", "one"]);
+        git(temp.path(), &["commit", "-q", "--allow-empty", "-m", "two"]);
+        git(
+            temp.path(),
+            &["update-ref", "refs/remotes/origin/main", "HEAD"],
+        );
+        git(temp.path(), &["checkout", "-q", "--detach", "HEAD~1"]);
+        std::fs::write(temp.path().join(".git/MERGE_HEAD"), "").unwrap();
+        let state = RepoState::load(temp.path()).unwrap();
+        assert!(matches!(state.head, HeadState::Detached { .. }));
+        assert_eq!(state.unpushed, Vec::<String>::new());
+        assert_eq!(state.operation, Some(Operation::Merge));
+        assert_eq!(state.status(), RepoStatus::Clean);
+        assert_eq!(state.details()[0], "merge in progress");
+    }
+
+    #[test]
+    fn unpushed_without_remotes_or_with_fetched_tags() {
+        let temp = TempDir::new().unwrap();
+        let origin = temp.path().join("origin");
+        let repo = temp.path().join("repo");
+        std::fs::create_dir_all(&origin).unwrap();
+        git(&origin, &["init", "-q", "-b", "main"]);
+        git(&origin, &["commit", "-q", "--allow-empty", "-m", "one"]);
+        git(&origin, &["branch", "feature"]);
+        let state = RepoState::load(&origin).unwrap();
+        assert_eq!(state.unpushed, ["feature", "main"]);
+        assert_eq!(state.status(), RepoStatus::Unpushed);
+
+        // A release tagged off to the side, which no branch contains
+        git(&origin, &["checkout", "-q", "--detach"]);
+        git(&origin, &["commit", "-q", "--allow-empty", "-m", "release"]);
+        git(&origin, &["tag", "v1"]);
+        git(&origin, &["checkout", "-q", "main"]);
+        git(
+            temp.path(),
+            &["clone", "-q", origin.to_str().unwrap(), "repo"],
+        );
+        git(&repo, &["fetch", "-q", "--tags"]);
+        git(&repo, &["tag", "mine", "v1"]);
+        git(&repo, &["commit", "-q", "--allow-empty", "-m", "local"]);
+        git(&repo, &["tag", "wip"]);
+        let state = RepoState::load(&repo).unwrap();
+        assert_eq!(state.unpushed, ["main", "wip"]);
+    }
+}
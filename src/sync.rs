@@ -4,9 +4,13 @@
 //! has already published to at least one remote out to the remotes that are
 //! behind. Commits that exist only locally are never pushed automatically.
 //!
+//! Repos restored from the library can also be brought up to date with
+//! [`fetch_and_fast_forward`].
+//!
 //! gix has no push support yet, so all network operations shell out to the
 //! `git` CLI (consistent with the existing `gh`/`glab` shell-outs).
 
+use crate::config::SyncConfig;
 use anyhow::{Result, bail};
 use std::collections::BTreeMap;
 use std::path::Path;
@@ -14,8 +18,6 @@ use std::process::{Command, Output, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 
-/// Timeout for git commands that hit the network (fetch, push, ls-remote)
-const NETWORK_TIMEOUT: Duration = Duration::from_secs(60);
 /// Timeout for purely local git commands
 const LOCAL_TIMEOUT: Duration = Duration::from_secs(10);
 
@@ -84,6 +86,8 @@ pub struct SyncOutcome {
     pub push_errors: Vec<(String, String, String)>,
     /// Fresh status computed after fetching (tracking refs are up to date)
     pub status: Option<crate::RepoStatus>,
+    /// The detailed state the status was derived from
+    pub state: Option<crate::RepoState>,
     pub modification_time: Option<std::time::SystemTime>,
 }
 
@@ -191,8 +195,13 @@ pub fn plan_ref_sync(
 ///
 /// Blocking; intended to run on a background thread. `interrupt` is checked
 /// between git invocations and aborts the ones in flight.
-pub fn sync_repo(repo_path: &Path, interrupt: &AtomicBool) -> Result<SyncOutcome> {
+pub fn sync_repo(
+    repo_path: &Path,
+    config: &SyncConfig,
+    interrupt: &AtomicBool,
+) -> Result<SyncOutcome> {
     let mut outcome = SyncOutcome::default();
+    let network_timeout = config.network_timeout();
 
     let remotes = list_remotes(repo_path, interrupt)?;
     if !remotes.is_empty() {
@@ -205,7 +214,7 @@ pub fn sync_repo(repo_path: &Path, interrupt: &AtomicBool) -> Result<SyncOutcome
                 repo_path,
                 &["fetch", "--prune", "--quiet", remote],
                 interrupt,
-                NETWORK_TIMEOUT,
+                network_timeout,
             ) {
                 Ok(out) if out.status.success() => fetched.push(remote.clone()),
                 Ok(out) => outcome
@@ -220,25 +229,171 @@ pub fn sync_repo(repo_path: &Path, interrupt: &AtomicBool) -> Result<SyncOutcome
         if fetched.is_empty() {
             outcome.fetch_errors.clear();
         } else if fetched.len() >= 2 {
-            mirror_refs(repo_path, &fetched, interrupt, &mut outcome)?;
+            mirror_refs(
+                repo_path,
+                &fetched,
+                network_timeout,
+                interrupt,
+                &mut outcome,
+            )?;
         }
     }
 
-    let (status, modification_time) = crate::check_repo_status_and_modification_time(repo_path)
-        .unwrap_or((crate::RepoStatus::NoCommits, None));
-    outcome.status = Some(status);
+    let (state, modification_time) =
+        crate::check_repo_state_and_modification_time(repo_path).unwrap_or((None, None));
+    outcome.status = Some(
+        state
+            .as_ref()
+            .map_or(crate::RepoStatus::NoCommits, crate::RepoState::status),
+    );
+    outcome.state = state;
     outcome.modification_time = modification_time;
     Ok(outcome)
 }
 
+/// What fetching a repo's remotes and fast-forwarding its branch changed
+#[derive(Debug, Default)]
+pub struct FetchOutcome {
+    /// (remote, error) for remotes that could not be fetched
+    pub fetch_errors: Vec<(String, String)>,
+    /// True when the repo has remotes but none of them could be reached
+    pub offline: bool,
+    /// Remote-tracking refs that were created or moved, e.g. "origin/main"
+    pub updated_refs: Vec<String>,
+    /// (branch, number of commits) when the checked-out branch was
+    /// fast-forwarded to its upstream
+    pub fast_forwarded: Option<(String, usize)>,
+    /// Why the checked-out branch was left behind its upstream
+    pub not_fast_forwarded: Option<String>,
+}
+
+/// Fetch every remote of the repo, then fast-forward the checked-out branch
+/// to its upstream if it's strictly behind and has no local changes.
+///
+/// Unreachable remotes are reported in the outcome rather than failing, so a
+/// repo restored while offline is simply left as it was.
+pub fn fetch_and_fast_forward(
+    repo_path: &Path,
+    config: &SyncConfig,
+    interrupt: &AtomicBool,
+) -> Result<FetchOutcome> {
+    let mut outcome = FetchOutcome::default();
+    let remotes = list_remotes(repo_path, interrupt)?;
+    if remotes.is_empty() {
+        return Ok(outcome);
+    }
+
+    let before = tracking_refs(repo_path, interrupt)?;
+    for remote in &remotes {
+        if interrupt.load(Ordering::Relaxed) {
+            bail!("interrupted");
+        }
+        match run_git(
+            repo_path,
+            &["fetch", "--prune", "--quiet", remote],
+            interrupt,
+            config.network_timeout(),
+        ) {
+            Ok(out) if out.status.success() => {}
+            Ok(out) => outcome
+                .fetch_errors
+                .push((remote.clone(), stderr_summary(&out))),
+            Err(e) => outcome.fetch_errors.push((remote.clone(), e.to_string())),
+        }
+    }
+    if outcome.fetch_errors.len() == remotes.len() {
+        outcome.offline = true;
+        return Ok(outcome);
+    }
+
+    let after = tracking_refs(repo_path, interrupt)?;
+    outcome.updated_refs = after
+        .iter()
+        .filter(|(name, id)| before.get(*name) != Some(id))
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    let git = |args: &[&str]| -> Result<Option<String>> {
+        let out = run_git(repo_path, args, interrupt, LOCAL_TIMEOUT)?;
+        Ok(out
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string()))
+    };
+    // A detached HEAD or a branch without an upstream is left alone
+    let Some(branch) = git(&["symbolic-ref", "--quiet", "--short", "HEAD"])? else {
+        return Ok(outcome);
+    };
+    let Some(upstream) = git(&["rev-parse", "--verify", "--quiet", "@{upstream}"])? else {
+        return Ok(outcome);
+    };
+    let Some(head) = git(&["rev-parse", "--verify", "--quiet", "HEAD"])? else {
+        return Ok(outcome);
+    };
+
+    match compare_ancestry(repo_path, &head, &upstream, interrupt) {
+        Ancestry::Equal | Ancestry::LocalAhead => {}
+        Ancestry::Diverged => {
+            outcome.not_fast_forwarded = Some("diverged from its upstream".to_string());
+        }
+        Ancestry::LocalBehind => {
+            let changes = git(&["status", "--porcelain", "--untracked-files=no"])?;
+            if changes.is_none_or(|c| !c.is_empty()) {
+                outcome.not_fast_forwarded = Some("has uncommitted changes".to_string());
+                return Ok(outcome);
+            }
+            let range = format!("{}..{}", head, upstream);
+            let commits = git(&["rev-list", "--count", &range])?
+                .and_then(|c| c.parse().ok())
+                .unwrap_or(0);
+            let out = run_git(
+                repo_path,
+                &["merge", "--ff-only", "--quiet", &upstream],
+                interrupt,
+                LOCAL_TIMEOUT,
+            )?;
+            if out.status.success() {
+                outcome.fast_forwarded = Some((branch, commits));
+            } else {
+                outcome.not_fast_forwarded = Some(stderr_summary(&out));
+            }
+        }
+    }
+    Ok(outcome)
+}
+
+/// Ids of the remote-tracking refs, keyed by short name like "origin/main"
+fn tracking_refs(repo_path: &Path, interrupt: &AtomicBool) -> Result<BTreeMap<String, String>> {
+    let out = run_git(
+        repo_path,
+        &[
+            "for-each-ref",
+            "--format=%(objectname) %(refname)",
+            "refs/remotes",
+        ],
+        interrupt,
+        LOCAL_TIMEOUT,
+    )?;
+    Ok(String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter(|(_, refname)| !refname.ends_with("/HEAD"))
+        .map(|(id, refname)| {
+            let name = refname.strip_prefix("refs/remotes/").unwrap_or(refname);
+            (name.to_string(), id.to_string())
+        })
+        .collect())
+}
+
 /// Plan and execute mirror pushes between the successfully fetched remotes
 fn mirror_refs(
     repo_path: &Path,
     remotes: &[String],
+    network_timeout: Duration,
     interrupt: &AtomicBool,
     outcome: &mut SyncOutcome,
 ) -> Result<()> {
-    let ref_states = collect_ref_states(repo_path, remotes, interrupt)?;
+    let ref_states = collect_ref_states(repo_path, remotes, network_timeout, interrupt)?;
 
     let mut ancestry =
         |local: &str, other: &str| compare_ancestry(repo_path, local, other, interrupt);
@@ -262,7 +417,14 @@ fn mirror_refs(
         if interrupt.load(Ordering::Relaxed) {
             bail!("interrupted");
         }
-        push_refs(repo_path, &remote, &refnames, interrupt, outcome);
+        push_refs(
+            repo_path,
+            &remote,
+            &refnames,
+            network_timeout,
+            interrupt,
+            outcome,
+        );
     }
     Ok(())
 }
@@ -271,6 +433,7 @@ fn mirror_refs(
 fn collect_ref_states(
     repo_path: &Path,
     remotes: &[String],
+    network_timeout: Duration,
     interrupt: &AtomicBool,
 ) -> Result<Vec<RefState>> {
     let out = run_git(
@@ -332,7 +495,7 @@ fn collect_ref_states(
                 repo_path,
                 &["ls-remote", "--tags", remote],
                 interrupt,
-                NETWORK_TIMEOUT,
+                network_timeout,
             )?;
             if !out.status.success() {
                 bail!(
@@ -408,6 +571,7 @@ fn push_refs(
     repo_path: &Path,
     remote: &str,
     refnames: &[String],
+    network_timeout: Duration,
     interrupt: &AtomicBool,
     outcome: &mut SyncOutcome,
 ) {
@@ -415,7 +579,7 @@ fn push_refs(
     let mut args = vec!["push", "--porcelain", remote];
     args.extend(refspecs.iter().map(|s| s.as_str()));
 
-    let out = match run_git(repo_path, &args, interrupt, NETWORK_TIMEOUT) {
+    let out = match run_git(repo_path, &args, interrupt, network_timeout) {
         Ok(out) => out,
         Err(e) => {
             for refname in refnames {
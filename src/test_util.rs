@@ -0,0 +1,21 @@
+//! Helpers shared by the unit tests.
+
+use std::path::Path;
+
+/// Run git in `path` as a test user, failing the test if it fails
+pub fn git(path: &Path, args: &[&str]) {
+    git_with_env(path, args, &[]);
+}
+
+/// [`git`] with extra environment variables, like fixed commit dates
+pub fn git_with_env(path: &Path, args: &[&str], env: &[(&str, &str)]) {
+    let status = std::process::Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .envs(env.iter().copied())
+        .current_dir(path)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "git {:?}", args);
+}
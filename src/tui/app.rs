@@ -50,7 +50,14 @@ pub struct App {
     pub matcher: SkimMatcherV2,
     pub workspace_path: String,
     pub loading_progress: Option<String>,
-    pub watch_disabled: bool,
+    /// What the file watcher can't see, when it had to fall back to fewer
+    /// watches or to polling
+    pub watch_limitations: Option<String>,
+    /// Whether workspace repos show how much disk space they take
+    pub show_sizes: bool,
+    /// Disk usage per workspace repo display name, measured on demand since
+    /// it means reading every file
+    repo_sizes: std::collections::HashMap<String, u64>,
     pub mode: AppMode,
     pub clone_repo_input: String,
     pub clone_repo_suggestions: Vec<String>,
@@ -86,7 +93,9 @@ impl App {
             matcher: SkimMatcherV2::default(),
             workspace_path,
             loading_progress: None,
-            watch_disabled: false,
+            watch_limitations: None,
+            show_sizes: false,
+            repo_sizes: std::collections::HashMap::new(),
             mode: AppMode::Normal,
             clone_repo_input: String::new(),
             clone_repo_suggestions: Vec::new(),
@@ -138,6 +147,9 @@ impl App {
     fn workspace_repos_with_overlays(&self) -> Vec<RepoInfo> {
         let mut repos = self.workspace_repos_list.clone();
         for repo in &mut repos {
+            if self.show_sizes {
+                repo.size_bytes = self.repo_sizes.get(&repo.display_name).copied();
+            }
             if let Some(status) = self.sync_statuses.get(&repo.display_name)
                 && matches!(
                     repo.operation_status,
@@ -161,12 +173,15 @@ impl App {
                     )),
                     display_name: pending.display_name.clone(),
                     status: None,
+                    state: None,
                     modification_time: None,
                     size_bytes: None,
                     operation_status: pending.status.clone(),
                     is_submodule: false,
                     submodule_initialized: false,
                     parent_repo_path: None,
+                    is_worktree: false,
+                    is_nested: false,
                 });
             }
         }
@@ -431,6 +446,7 @@ impl App {
         &mut self,
         display_name: &str,
         status: crate::RepoStatus,
+        state: Option<crate::RepoState>,
         modification_time: Option<std::time::SystemTime>,
     ) {
         if let Some(repo) = self
@@ -439,6 +455,7 @@ impl App {
             .find(|r| r.display_name == display_name)
         {
             repo.status = Some(status);
+            repo.state = state.map(Box::new);
             if modification_time.is_some() {
                 repo.modification_time = modification_time;
             }
@@ -446,16 +463,77 @@ impl App {
         }
     }
 
+    /// Show or hide the size of each workspace repo
+    pub fn toggle_sizes(&mut self) {
+        self.show_sizes = !self.show_sizes;
+        self.rebuild_after_pending_change();
+    }
+
+    /// Record the measured disk usage of the given repo
+    pub fn set_repo_size(&mut self, display_name: &str, bytes: u64) {
+        self.repo_sizes.insert(display_name.to_string(), bytes);
+        if self.show_sizes {
+            self.rebuild_after_pending_change();
+        }
+    }
+
+    /// Forget the sizes of repos that changed, so they're measured again
+    pub fn forget_repo_sizes(&mut self, paths: &[PathBuf]) {
+        let names: Vec<&String> = self
+            .workspace_repos_list
+            .iter()
+            .filter(|r| paths.contains(&r.path))
+            .map(|r| &r.display_name)
+            .collect();
+        self.repo_sizes.retain(|name, _| !names.contains(&name));
+    }
+
+    /// Display names and paths of the workspace repos whose size is shown
+    /// but not measured yet. Submodules are part of their parent's size.
+    pub fn repos_missing_size(&self) -> Vec<(String, PathBuf)> {
+        if !self.show_sizes {
+            return Vec::new();
+        }
+        self.workspace_repos_list
+            .iter()
+            .filter(|r| {
+                !r.is_submodule
+                    && r.operation_status != RepoOperationStatus::Scanning
+                    && !self.repo_sizes.contains_key(&r.display_name)
+            })
+            .map(|r| (r.display_name.clone(), r.path.clone()))
+            .collect()
+    }
+
     /// Paths of the workspace repos that background sync should consider
-    /// (submodules are synced through their parent repo)
+    /// (submodules are synced through their parent repo, and linked worktrees
+    /// share theirs with the main repo)
     pub fn syncable_repo_paths(&self) -> Vec<PathBuf> {
         self.workspace_repos_list
             .iter()
-            .filter(|r| !r.is_submodule)
+            .filter(|r| !r.is_submodule && !r.is_worktree)
             .map(|r| r.path.clone())
             .collect()
     }
 
+    /// The workspace repos to rescan for changes in the given repos, or
+    /// `None` if any of them isn't listed and the whole workspace needs
+    /// reloading. Submodules are rescanned along with their parent repo.
+    pub fn repos_to_rescan(&self, changed: &[PathBuf]) -> Option<Vec<PathBuf>> {
+        let mut repos: Vec<PathBuf> = Vec::new();
+        for path in changed {
+            let row = self.workspace_repos_list.iter().find(|r| &r.path == path)?;
+            let repo = match &row.parent_repo_path {
+                Some(parent) if row.is_submodule => parent,
+                _ => &row.path,
+            };
+            if !repos.contains(repo) {
+                repos.push(repo.clone());
+            }
+        }
+        Some(repos)
+    }
+
     /// Snapshot of the current repo lists, used to seed a background refresh
     /// so existing rows keep their data while being rescanned
     pub fn repo_snapshot(&self) -> (Vec<RepoInfo>, Vec<RepoInfo>) {
@@ -530,12 +608,15 @@ mod tests {
             path: PathBuf::from(display_name),
             display_name: display_name.to_string(),
             status: Some(crate::RepoStatus::Clean),
+            state: None,
             modification_time: None,
             size_bytes: None,
             operation_status: super::super::tree::RepoOperationStatus::None,
             is_submodule: false,
             submodule_initialized: false,
             parent_repo_path: None,
+            is_worktree: false,
+            is_nested: false,
         }
     }
 
@@ -601,6 +682,7 @@ mod tests {
         app.apply_scan_result(
             "github.com/foo/app",
             crate::RepoStatus::Unpushed,
+            None,
             Some(time),
         );
 
@@ -609,6 +691,118 @@ mod tests {
         assert_eq!(info.modification_time, Some(time));
     }
 
+    #[test]
+    fn worktrees_nest_under_their_main_repo() {
+        let mut worktree = repo("app-feature");
+        worktree.is_worktree = true;
+        worktree.parent_repo_path = Some(PathBuf::from("app"));
+        let mut orphan = repo("other-feature");
+        orphan.is_worktree = true;
+        orphan.parent_repo_path = Some(PathBuf::from("elsewhere/other"));
+        let app = App::new(
+            "workspace".to_string(),
+            vec![worktree, repo("app"), orphan],
+            Vec::new(),
+        );
+
+        let nodes = flatten_trees(&app.filtered_workspace);
+        let (app_node, _, _, _) = nodes.iter().find(|(_, _, _, p)| p == "app").unwrap();
+        assert_eq!(app_node.children.len(), 1);
+        assert_eq!(app_node.children[0].name, "app-feature");
+        // Dropping the whole node takes the worktree first
+        assert_eq!(app_node.collect_repo_paths(), vec!["app-feature", "app"]);
+        // A worktree whose main repo isn't listed stays at the top level
+        assert!(
+            app.filtered_workspace
+                .iter()
+                .any(|n| n.name == "other-feature")
+        );
+        assert_eq!(app.syncable_repo_paths(), vec![PathBuf::from("app")]);
+    }
+
+    #[test]
+    fn nested_repos_nest_under_the_repo_they_are_in() {
+        let mut nested = repo("app/deps/lib");
+        nested.is_nested = true;
+        nested.parent_repo_path = Some(PathBuf::from("app"));
+        let mut inner = repo("app/deps/lib/inner");
+        inner.is_nested = true;
+        inner.parent_repo_path = Some(PathBuf::from("app/deps/lib"));
+        let app = App::new(
+            "workspace".to_string(),
+            vec![inner, nested, repo("app")],
+            Vec::new(),
+        );
+
+        let (app_node, _, _, _) = flatten_trees(&app.filtered_workspace)
+            .into_iter()
+            .find(|(_, _, _, p)| p == "app")
+            .unwrap();
+        assert_eq!(app_node.children.len(), 1);
+        assert_eq!(app_node.children[0].name, "deps/lib");
+        assert_eq!(app_node.children[0].children[0].name, "inner");
+        assert_eq!(
+            app_node.collect_repo_paths(),
+            vec!["app/deps/lib/inner", "app/deps/lib", "app"]
+        );
+    }
+
+    #[test]
+    fn changed_repos_map_to_workspace_rows() {
+        let mut submodule = repo("app/lib");
+        submodule.is_submodule = true;
+        submodule.parent_repo_path = Some(PathBuf::from("app"));
+        let app = App::new(
+            "workspace".to_string(),
+            vec![repo("app"), submodule, repo("tool")],
+            Vec::new(),
+        );
+
+        assert_eq!(
+            app.repos_to_rescan(&[PathBuf::from("app/lib"), PathBuf::from("app")]),
+            Some(vec![PathBuf::from("app")])
+        );
+        assert_eq!(
+            app.repos_to_rescan(&[PathBuf::from("tool")]),
+            Some(vec![PathBuf::from("tool")])
+        );
+        // A repo that isn't listed yet means the repo set changed
+        assert_eq!(
+            app.repos_to_rescan(&[PathBuf::from("tool"), PathBuf::from("new")]),
+            None
+        );
+    }
+
+    #[test]
+    fn sizes_are_measured_once_shown() {
+        let mut submodule = repo("app/lib");
+        submodule.is_submodule = true;
+        submodule.parent_repo_path = Some(PathBuf::from("app"));
+        let mut app = App::new(
+            "workspace".to_string(),
+            vec![repo("app"), submodule],
+            Vec::new(),
+        );
+        assert!(app.repos_missing_size().is_empty());
+
+        app.toggle_sizes();
+        assert_eq!(
+            app.repos_missing_size(),
+            vec![("app".to_string(), PathBuf::from("app"))]
+        );
+        app.set_repo_size("app", 4096);
+        assert!(app.repos_missing_size().is_empty());
+        let (app_node, _, _, _) = flatten_trees(&app.filtered_workspace)
+            .into_iter()
+            .find(|(_, _, _, p)| p == "app")
+            .unwrap();
+        assert_eq!(app_node.repo_info.as_ref().unwrap().size_bytes, Some(4096));
+
+        // A repo that changed is measured again
+        app.forget_repo_sizes(&[PathBuf::from("app")]);
+        assert_eq!(app.repos_missing_size().len(), 1);
+    }
+
     #[test]
     fn pending_clone_appears_and_resolves() {
         let mut app = App::new(
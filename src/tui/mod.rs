@@ -4,10 +4,12 @@ mod tree;
 mod watcher;
 
 use app::{App, AppMode, Section};
-use metadata::{format_size, format_time_ago_verbose, get_repo_modification_time, get_repo_size};
+use metadata::{format_size, format_time_ago_verbose, get_repo_modification_time};
 use tree::{RepoInfo, RepoOperationStatus, TreeNode};
 use watcher::FileWatcher;
 
+use crate::cache::{CachedState, Freshness, StatusCache};
+use crate::config::SyncConfig;
 use crate::{RepoPattern, Workspace, find_git_repositories};
 use anyhow::{Result, anyhow};
 use crossterm::{
@@ -33,10 +35,6 @@ use std::time::{Duration, Instant};
 
 const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 
-/// How many repos may sync (fetch/push) concurrently
-const MAX_CONCURRENT_SYNCS: usize = 4;
-/// How often all repos are re-checked against their remotes
-const SYNC_INTERVAL: Duration = Duration::from_secs(300);
 /// Ignore watcher-triggered sync requests this soon after a sync finished,
 /// since the sync's own fetch writes the tracking refs the watcher observes
 const WATCHER_SYNC_COOLDOWN: Duration = Duration::from_secs(2);
@@ -48,6 +46,8 @@ enum Action {
     RestoreFromLibrary(Vec<String>),
     CloneRepo(String),
     RefreshData,
+    /// Rescan only the given workspace repos, whose files changed
+    RescanRepos(Vec<PathBuf>),
 }
 
 /// Results streamed from the background repo scan
@@ -67,7 +67,8 @@ enum LoadEvent {
 ///
 /// Rows the scan hasn't reached yet are shown with a "scanning" status:
 /// seeded from the app's previous data on a refresh (keeping their last known
-/// status), or as bare placeholders on the initial load.
+/// status), or as bare placeholders on the initial load. A rescan of a few
+/// changed repos leaves every other row as it is.
 struct RepoLoader {
     rx: mpsc::Receiver<LoadEvent>,
     scanned_workspace: Vec<RepoInfo>,
@@ -108,6 +109,41 @@ impl RepoLoader {
         }
     }
 
+    /// Rescan just the given workspace repos (and their submodules), keeping
+    /// the rest of the seeded rows
+    fn rescan(
+        workspace: &Workspace,
+        repos: Vec<PathBuf>,
+        seed_workspace: Vec<RepoInfo>,
+        seed_library: Vec<RepoInfo>,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let total = repos.len();
+        let (mut pending_workspace, scanned_workspace): (Vec<_>, Vec<_>) =
+            seed_workspace.into_iter().partition(|r| {
+                let repo = match &r.parent_repo_path {
+                    Some(parent) if r.is_submodule => parent,
+                    _ => &r.path,
+                };
+                repos.contains(repo)
+            });
+        for repo in &mut pending_workspace {
+            repo.operation_status = RepoOperationStatus::Scanning;
+        }
+        let workspace = workspace.clone();
+        std::thread::spawn(move || scan_changed_repos(&workspace, repos, tx));
+        Self {
+            rx,
+            scanned_workspace,
+            scanned_library: seed_library,
+            pending_workspace,
+            pending_library: Vec::new(),
+            done: 0,
+            total,
+            progressive: false,
+        }
+    }
+
     /// Drain any newly scanned repos into the app without blocking.
     /// Returns false once the scan has finished.
     fn poll(&mut self, app: &mut App) -> bool {
@@ -187,13 +223,14 @@ fn merge_discovered(pending: &mut Vec<RepoInfo>, discovered: Vec<RepoInfo>) {
 /// Result of a background sync job for one repo
 enum SyncEvent {
     Started,
-    Finished(crate::sync::SyncOutcome),
+    Finished(Box<crate::sync::SyncOutcome>),
     Failed(String),
 }
 
 /// Schedules background jobs that mirror each repo's commits across its
-/// remotes. Jobs run on their own threads (up to `MAX_CONCURRENT_SYNCS`) and
-/// report back through a channel drained by the event loop.
+/// remotes. Jobs run on their own threads (up to `sync.max_concurrent` from
+/// the workspace config) and report back through a channel drained by the
+/// event loop.
 struct SyncManager {
     tx: mpsc::Sender<(PathBuf, SyncEvent)>,
     rx: mpsc::Receiver<(PathBuf, SyncEvent)>,
@@ -210,10 +247,11 @@ struct SyncManager {
     startup_pending: bool,
     interrupt: Arc<AtomicBool>,
     workspace_path: String,
+    config: Arc<SyncConfig>,
 }
 
 impl SyncManager {
-    fn new(workspace_path: String) -> Self {
+    fn new(workspace_path: String, config: SyncConfig) -> Self {
         let (tx, rx) = mpsc::channel();
         Self {
             tx,
@@ -226,6 +264,7 @@ impl SyncManager {
             startup_pending: true,
             interrupt: Arc::new(AtomicBool::new(false)),
             workspace_path,
+            config: Arc::new(config),
         }
     }
 
@@ -258,7 +297,7 @@ impl SyncManager {
     /// Re-check all repos on a timer; skipped while a scan is loading since
     /// the repo list may be incomplete
     fn maybe_periodic(&mut self, app: &App, loader_active: bool) {
-        if loader_active || self.last_periodic.elapsed() < SYNC_INTERVAL {
+        if loader_active || self.last_periodic.elapsed() < self.config.interval() {
             return;
         }
         self.last_periodic = Instant::now();
@@ -267,17 +306,18 @@ impl SyncManager {
 
     /// Spawn queued jobs up to the concurrency cap
     fn pump(&mut self) {
-        while self.in_flight.len() < MAX_CONCURRENT_SYNCS {
+        while self.in_flight.len() < self.config.max_concurrent {
             let Some(repo) = self.queue.pop_front() else {
                 break;
             };
             self.in_flight.insert(repo.clone());
             let tx = self.tx.clone();
             let interrupt = self.interrupt.clone();
+            let config = self.config.clone();
             std::thread::spawn(move || {
                 let _ = tx.send((repo.clone(), SyncEvent::Started));
-                let event = match crate::sync::sync_repo(&repo, &interrupt) {
-                    Ok(outcome) => SyncEvent::Finished(outcome),
+                let event = match crate::sync::sync_repo(&repo, &config, &interrupt) {
+                    Ok(outcome) => SyncEvent::Finished(Box::new(outcome)),
                     Err(e) => SyncEvent::Failed(e.to_string()),
                 };
                 let _ = tx.send((repo, event));
@@ -296,7 +336,12 @@ impl SyncManager {
                 SyncEvent::Finished(outcome) => {
                     self.finish(&repo);
                     if let Some(status) = outcome.status {
-                        app.apply_scan_result(&display_name, status, outcome.modification_time);
+                        app.apply_scan_result(
+                            &display_name,
+                            status,
+                            outcome.state.clone(),
+                            outcome.modification_time,
+                        );
                     }
                     match outcome.error_summary() {
                         Some(err) => {
@@ -331,9 +376,16 @@ struct BackgroundTasks {
     clone_result: Option<(String, mpsc::Receiver<Option<String>>)>,
     /// Delivers the file watcher once its (potentially slow) recursive
     /// registration of the workspace tree completes
-    watcher: Option<mpsc::Receiver<Result<FileWatcher, notify::Error>>>,
+    watcher: Option<mpsc::Receiver<FileWatcher>>,
     /// Mirrors commits across each repo's remotes
     sync: SyncManager,
+    /// Repos the watcher saw change, waiting for the running scan to finish
+    changed_repos: Vec<PathBuf>,
+    /// Whether the watcher saw the repo set change, so everything needs
+    /// reloading once the running scan finishes
+    reload_pending: bool,
+    /// Delivers the disk usage of workspace repos as each is measured
+    sizes: Option<mpsc::Receiver<(String, u64)>>,
 }
 
 /// Detect the parent shell by reading /proc/self/status
@@ -385,11 +437,13 @@ pub fn run_tui(workspace: &Workspace) -> Result<()> {
         // recursively registering a large workspace can take a while and would
         // delay the first frame
         let workspace_path = PathBuf::from(&workspace.path);
+        let watch_config = workspace.config.watch.clone();
         let (watcher_tx, watcher_rx) = mpsc::channel();
         std::thread::spawn(move || {
-            let _ = watcher_tx.send(FileWatcher::new(
+            let _ = watcher_tx.send(FileWatcher::start(
                 &workspace_path,
                 Duration::from_millis(500),
+                &watch_config,
             ));
         });
         let mut file_watcher: Option<FileWatcher> = None;
@@ -399,7 +453,10 @@ pub fn run_tui(workspace: &Workspace) -> Result<()> {
             suggestions: None,
             clone_result: None,
             watcher: Some(watcher_rx),
-            sync: SyncManager::new(workspace.path.clone()),
+            sync: SyncManager::new(workspace.path.clone(), workspace.config.sync.clone()),
+            changed_repos: Vec::new(),
+            reload_pending: false,
+            sizes: None,
         };
 
         // Inner loop to handle actions without tearing down terminal
@@ -458,7 +515,7 @@ pub fn run_tui(workspace: &Workspace) -> Result<()> {
                         RepoOperationStatus::Dropping,
                         |repo_path| {
                             let Ok(pattern) = repo_path.parse::<RepoPattern>();
-                            workspace.drop(&pattern, false, false)
+                            workspace.drop(&pattern, false, false, false, false)
                         },
                     )?;
                     let (seed_workspace, seed_library) = app.repo_snapshot();
@@ -513,6 +570,19 @@ pub fn run_tui(workspace: &Workspace) -> Result<()> {
                         watcher.drain_pending();
                     }
                 }
+                Action::RescanRepos(repos) => {
+                    app.forget_repo_sizes(&repos);
+                    let (seed_workspace, seed_library) = app.repo_snapshot();
+                    background.loader = Some(RepoLoader::rescan(
+                        workspace,
+                        repos,
+                        seed_workspace,
+                        seed_library,
+                    ));
+                    if let Some(watcher) = file_watcher.as_mut() {
+                        watcher.drain_pending();
+                    }
+                }
             }
         }
     }
@@ -564,14 +634,11 @@ fn run_app<B: ratatui::backend::Backend>(
         // Apply results from background work before drawing
         if let Some(rx) = &background.watcher {
             match rx.try_recv() {
-                Ok(Ok(watcher)) => {
+                Ok(watcher) => {
+                    app.watch_limitations = watcher.limitations();
                     *file_watcher = Some(watcher);
                     background.watcher = None;
                 }
-                Ok(Err(_)) => {
-                    app.watch_disabled = true;
-                    background.watcher = None;
-                }
                 Err(mpsc::TryRecvError::Empty) => {}
                 Err(mpsc::TryRecvError::Disconnected) => background.watcher = None,
             }
@@ -595,6 +662,7 @@ fn run_app<B: ratatui::backend::Backend>(
         background.sync.maybe_periodic(app, loader_active);
         background.sync.pump();
         poll_suggestions(app, background);
+        poll_sizes(app, background);
         if let Some((pattern, rx)) = &background.clone_result {
             match rx.try_recv() {
                 Ok(error) => {
@@ -618,16 +686,33 @@ fn run_app<B: ratatui::backend::Backend>(
 
         // Check for filesystem changes. Remote-tracking ref updates (e.g. a
         // push from another terminal) trigger a sync check for that repo;
-        // worktree changes trigger a reload unless one is already running
+        // worktree changes trigger a rescan of the repos they're in, or a
+        // full reload when repos may have come or gone. Either waits for a
+        // scan that's already running.
         if let Some(watcher) = file_watcher.as_mut() {
             let signals = watcher.poll();
+            // New directories can use up the last watches
+            app.watch_limitations = watcher.limitations();
             for repo in signals.refs_changed {
                 background.sync.request_sync(repo, true);
             }
-            if signals.refresh && background.loader.is_none() {
-                return Ok(Action::RefreshData);
+            background.reload_pending |= signals.refresh;
+            for repo in signals.changed_repos {
+                if !background.changed_repos.contains(&repo) {
+                    background.changed_repos.push(repo);
+                }
             }
         }
+        if background.loader.is_none()
+            && (background.reload_pending || !background.changed_repos.is_empty())
+        {
+            let changed = std::mem::take(&mut background.changed_repos);
+            let reload = std::mem::take(&mut background.reload_pending);
+            return Ok(match app.repos_to_rescan(&changed) {
+                Some(repos) if !reload => Action::RescanRepos(repos),
+                _ => Action::RefreshData,
+            });
+        }
 
         // Use poll with timeout to allow checking for filesystem updates periodically
         if event::poll(Duration::from_millis(100))? {
@@ -654,6 +739,10 @@ fn run_app<B: ratatui::backend::Backend>(
                     KeyCode::Right | KeyCode::Left => {
                         app.toggle_expand();
                     }
+                    KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        // Ctrl+S = show or hide workspace repo sizes
+                        app.toggle_sizes();
+                    }
                     KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                         // Ctrl+A = clone repo dialog; suggestions arrive from a
                         // background thread since gh/glab may hit the network
@@ -775,6 +864,47 @@ fn run_app<B: ratatui::backend::Backend>(
     }
 }
 
+/// Apply measured repo sizes, and start measuring the shown repos that have
+/// none once no scan is running (measuring reads every file, so it stays out
+/// of the scan's way)
+fn poll_sizes(app: &mut App, background: &mut BackgroundTasks) {
+    if let Some(rx) = &background.sizes {
+        loop {
+            match rx.try_recv() {
+                Ok((display_name, bytes)) => app.set_repo_size(&display_name, bytes),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    background.sizes = None;
+                    break;
+                }
+            }
+        }
+    }
+    if background.sizes.is_some() || background.loader.is_some() {
+        return;
+    }
+    let repos = app.repos_missing_size();
+    if repos.is_empty() {
+        return;
+    }
+    let (tx, rx) = mpsc::channel();
+    background.sizes = Some(rx);
+    std::thread::spawn(move || {
+        let cancel = AtomicBool::new(false);
+        crate::scan::for_each_parallel(
+            repos,
+            crate::scan::default_workers(),
+            &cancel,
+            |(display_name, path)| (display_name, crate::usage::repo_usage(&path).total()),
+            |size, _| {
+                if tx.send(size).is_err() {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            },
+        );
+    });
+}
+
 /// Apply clone-dialog suggestions once the background fetch completes
 fn poll_suggestions(app: &mut App, background: &mut BackgroundTasks) {
     let Some(rx) = &background.suggestions else {
@@ -873,6 +1003,7 @@ fn render_help_line(f: &mut Frame, app: &App, area: Rect) {
     ];
     if app.active_section == Section::Workspace {
         bindings.push(("Ctrl+D", Color::Yellow, " drop  "));
+        bindings.push(("Ctrl+S", Color::Blue, " sizes  "));
     }
     bindings.push(("Ctrl+A", Color::Magenta, " clone  "));
     bindings.push(("Esc", Color::Red, " quit"));
@@ -904,9 +1035,20 @@ fn render_tree_panel(f: &mut Frame, app: &App, area: Rect, section: Section) {
             workspace_panel_title(app, area.width),
             true,
             (|repo| {
-                repo.modification_time
+                let time = repo
+                    .modification_time
                     .map(format_time_ago_verbose)
-                    .unwrap_or_default()
+                    .unwrap_or_default();
+                // Ahead/behind, stashes and unfinished operations go first,
+                // then the size when sizes are shown
+                let mut metadata = match repo.state.as_ref().map(|state| state.compact()) {
+                    Some(compact) if !compact.is_empty() => format!("{}  {}", compact, time),
+                    _ => time,
+                };
+                if let Some(size) = repo.size_bytes {
+                    metadata = format!("{:>8}  {}", format_size(size), metadata);
+                }
+                metadata
             }) as IdleMetadata,
         ),
         Section::Library => (
@@ -966,8 +1108,8 @@ fn workspace_panel_title(app: &App, panel_width: u16) -> Line<'static> {
     if let Some(ref progress) = app.loading_progress {
         markers.push_str(&format!(" {}", progress));
     }
-    if app.watch_disabled {
-        markers.push_str(" [watch disabled]");
+    if let Some(ref limitations) = app.watch_limitations {
+        markers.push_str(&format!(" [{}]", limitations));
     }
 
     // Fit the path into what's left after borders, the count, and the markers
@@ -1046,17 +1188,13 @@ fn tree_list_item<'a>(
 
     // Add status icon for repos only
     if show_status_icons && let Some(ref repo) = node.repo_info {
-        if repo.is_submodule {
-            // Submodule indicator
-            if repo.submodule_initialized {
-                spans.push(Span::styled("S ", Style::default().fg(Color::Magenta)));
-            } else {
-                spans.push(Span::styled("S ", Style::default().fg(Color::DarkGray)));
-                spans.push(Span::styled(
-                    "(uninit) ",
-                    Style::default().fg(Color::DarkGray),
-                ));
-            }
+        if repo.is_submodule && !repo.submodule_initialized {
+            // Nothing checked out, so there's no status to show
+            spans.push(Span::styled("S ", Style::default().fg(Color::DarkGray)));
+            spans.push(Span::styled(
+                "(uninit) ",
+                Style::default().fg(Color::DarkGray),
+            ));
         } else {
             let (icon, color) = match repo.status {
                 // Not scanned yet (includes placeholder rows for clones)
@@ -1067,6 +1205,15 @@ fn tree_list_item<'a>(
                 Some(crate::RepoStatus::NoCommits) => ("· ", Color::DarkGray),
             };
             spans.push(Span::styled(icon, Style::default().fg(color)));
+            if repo.is_submodule {
+                spans.push(Span::styled("S ", Style::default().fg(Color::Magenta)));
+            }
+            if repo.is_worktree {
+                spans.push(Span::styled("W ", Style::default().fg(Color::Blue)));
+            }
+            if repo.is_nested {
+                spans.push(Span::styled("N ", Style::default().fg(Color::Cyan)));
+            }
         }
     }
 
@@ -1302,23 +1449,39 @@ fn workspace_display_name(workspace_path: &str, path: &Path) -> String {
         .to_string()
 }
 
-fn scan_workspace_repo(workspace_path: &str, path: PathBuf) -> Vec<RepoInfo> {
+fn scan_workspace_repo(
+    workspace_path: &str,
+    path: PathBuf,
+    cache: &Mutex<StatusCache>,
+) -> Vec<RepoInfo> {
     let display_name = workspace_display_name(workspace_path, &path);
 
-    // Check repo status and get modification time in a single repo open for performance
-    let (status, modification_time) = crate::check_repo_status_and_modification_time(&path)
-        .unwrap_or((crate::RepoStatus::NoCommits, None));
+    // Always rescan (the watcher asks for a rescan when something changed),
+    // refreshing the cache entry for the CLI on the way
+    let (state, modification_time) =
+        match crate::cache::lookup(cache, &display_name, &path, Freshness::Refresh) {
+            Ok(Some(scanned)) => (Some(scanned.state), scanned.modified),
+            _ => (None, None),
+        };
+    let status = state
+        .as_ref()
+        .map_or(crate::RepoStatus::NoCommits, crate::RepoState::status);
+    let worktree_main = crate::worktree_main(&path);
+    let nested_in = crate::discovery::nesting_repo(Path::new(workspace_path), &path);
 
     let mut infos = vec![RepoInfo {
         path: path.clone(),
         display_name: display_name.clone(),
         status: Some(status),
+        state: state.map(Box::new),
         modification_time,
         size_bytes: None, // Size not computed for workspace repos to save time
         operation_status: RepoOperationStatus::None,
         is_submodule: false,
         submodule_initialized: false,
-        parent_repo_path: None,
+        is_worktree: worktree_main.is_some(),
+        is_nested: worktree_main.is_none() && nested_in.is_some(),
+        parent_repo_path: worktree_main.or(nested_in),
     }];
 
     // Find and add submodules
@@ -1330,16 +1493,26 @@ fn scan_workspace_repo(workspace_path: &str, path: PathBuf) -> Vec<RepoInfo> {
                 format!("{}/{}", display_name, submodule.path.display())
             };
 
+            // Submodules aren't in the status cache, so they're always scanned
+            let submodule_path = path.join(&submodule.path);
+            let (state, modification_time) = if submodule.initialized {
+                crate::check_repo_state_and_modification_time(&submodule_path).unwrap_or_default()
+            } else {
+                (None, None)
+            };
             infos.push(RepoInfo {
-                path: path.join(&submodule.path),
+                path: submodule_path,
                 display_name: submodule_display_name,
-                status: Some(crate::RepoStatus::Clean), // Submodule status computed separately
-                modification_time: None,
+                status: state.as_ref().map(crate::RepoState::status),
+                state: state.map(Box::new),
+                modification_time,
                 size_bytes: None,
                 operation_status: RepoOperationStatus::None,
                 is_submodule: true,
                 submodule_initialized: submodule.initialized,
                 parent_repo_path: Some(path.clone()),
+                is_worktree: false,
+                is_nested: false,
             });
         }
     }
@@ -1348,18 +1521,26 @@ fn scan_workspace_repo(workspace_path: &str, path: PathBuf) -> Vec<RepoInfo> {
 }
 
 /// Scan a single library repository for its metadata
-fn scan_library_repo(library_path: &str, repo_path: String) -> RepoInfo {
+fn scan_library_repo(
+    library_path: &str,
+    repo_path: String,
+    dropped_at: Option<std::time::SystemTime>,
+) -> RepoInfo {
     let full_path = PathBuf::from(library_path).join(&repo_path);
     RepoInfo {
-        modification_time: get_repo_modification_time(&full_path).ok(),
-        size_bytes: get_repo_size(&full_path).ok(),
+        // Order the library by when repos were dropped where that's known
+        modification_time: dropped_at.or_else(|| get_repo_modification_time(&full_path).ok()),
+        size_bytes: Some(crate::usage::dir_size(&full_path)),
         path: full_path,
         display_name: repo_path,
         status: Some(crate::RepoStatus::Clean), // Library repos are always clean
+        state: None,
         operation_status: RepoOperationStatus::None,
         is_submodule: false,
         submodule_initialized: false,
         parent_repo_path: None,
+        is_worktree: false,
+        is_nested: false,
     }
 }
 
@@ -1369,27 +1550,55 @@ fn scan_library_repo(library_path: &str, repo_path: String) -> RepoInfo {
 fn scan_all_repos(workspace: &Workspace, tx: mpsc::Sender<LoadEvent>) {
     enum ScanTask {
         Workspace(PathBuf),
-        Library(String),
+        Library(String, Option<std::time::SystemTime>),
     }
 
     let workspace_paths = find_git_repositories(Path::new(&workspace.path)).unwrap_or_default();
     let library_paths = workspace.list_library().unwrap_or_default();
     let library_path = workspace.library_path();
+    let library_index =
+        crate::library::LibraryIndex::load(Path::new(&library_path)).unwrap_or_default();
+    let dropped_at = |repo_path: &str| {
+        library_index
+            .repos
+            .get(repo_path)
+            .and_then(|record| record.dropped_time())
+    };
+
+    // Every repo is rescanned, but the cached states give the rows their last
+    // known status in the meantime
+    let workspace_root = Path::new(&workspace.path);
+    let cache = StatusCache::load(workspace_root);
+    let workspace_names: Vec<String> = workspace_paths
+        .iter()
+        .map(|path| workspace_display_name(&workspace.path, path))
+        .collect();
 
     // Announce the full repo set before any git work so every row can render
     // with a "scanning" status right away
     let discovered_workspace = workspace_paths
         .iter()
-        .map(|path| RepoInfo {
-            path: path.clone(),
-            display_name: workspace_display_name(&workspace.path, path),
-            status: None,
-            modification_time: None,
-            size_bytes: None,
-            operation_status: RepoOperationStatus::Scanning,
-            is_submodule: false,
-            submodule_initialized: false,
-            parent_repo_path: None,
+        .zip(&workspace_names)
+        .map(|(path, display_name)| {
+            let cached = cache
+                .get(display_name, None, Freshness::Any)
+                .map(CachedState::from);
+            let worktree_main = crate::worktree_main(path);
+            let nested_in = crate::discovery::nesting_repo(workspace_root, path);
+            RepoInfo {
+                path: path.clone(),
+                display_name: display_name.clone(),
+                status: cached.as_ref().map(|c| c.state.status()),
+                modification_time: cached.as_ref().and_then(|c| c.modified),
+                state: cached.map(|c| Box::new(c.state)),
+                size_bytes: None,
+                operation_status: RepoOperationStatus::Scanning,
+                is_submodule: false,
+                submodule_initialized: false,
+                is_worktree: worktree_main.is_some(),
+                is_nested: worktree_main.is_none() && nested_in.is_some(),
+                parent_repo_path: worktree_main.or(nested_in),
+            }
         })
         .collect();
     let discovered_library = library_paths
@@ -1398,12 +1607,15 @@ fn scan_all_repos(workspace: &Workspace, tx: mpsc::Sender<LoadEvent>) {
             path: PathBuf::from(&library_path).join(repo_path),
             display_name: repo_path.clone(),
             status: Some(crate::RepoStatus::Clean),
-            modification_time: None,
+            state: None,
+            modification_time: dropped_at(repo_path),
             size_bytes: None,
             operation_status: RepoOperationStatus::Scanning,
             is_submodule: false,
             submodule_initialized: false,
             parent_repo_path: None,
+            is_worktree: false,
+            is_nested: false,
         })
         .collect();
     if tx
@@ -1416,46 +1628,63 @@ fn scan_all_repos(workspace: &Workspace, tx: mpsc::Sender<LoadEvent>) {
         return;
     }
 
-    let tasks: Mutex<Vec<ScanTask>> = Mutex::new(
+    let tasks =
         workspace_paths
             .into_iter()
             .map(ScanTask::Workspace)
-            .chain(library_paths.into_iter().map(ScanTask::Library))
-            .collect(),
+            .chain(library_paths.into_iter().map(|repo_path| {
+                let dropped_at = dropped_at(&repo_path);
+                ScanTask::Library(repo_path, dropped_at)
+            }));
+
+    // Stop scanning once the UI stops listening
+    let cancel = AtomicBool::new(false);
+    let cache = Mutex::new(cache);
+    crate::scan::for_each_parallel(
+        tasks,
+        crate::scan::default_workers(),
+        &cancel,
+        |task| match task {
+            ScanTask::Workspace(path) => {
+                LoadEvent::Workspace(scan_workspace_repo(&workspace.path, path, &cache))
+            }
+            ScanTask::Library(repo_path, dropped_at) => {
+                LoadEvent::Library(scan_library_repo(&library_path, repo_path, dropped_at))
+            }
+        },
+        |event, _| {
+            if tx.send(event).is_err() {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        },
     );
 
-    let workers = std::thread::available_parallelism()
-        .map(|n| n.get())
-        .unwrap_or(4)
-        .min(8);
-
-    std::thread::scope(|scope| {
-        for _ in 0..workers {
-            let tx = tx.clone();
-            let tasks = &tasks;
-            let workspace_path = workspace.path.as_str();
-            let library_path = library_path.as_str();
-            scope.spawn(move || {
-                loop {
-                    let task = tasks.lock().unwrap().pop();
-                    let Some(task) = task else {
-                        break;
-                    };
-                    let event = match task {
-                        ScanTask::Workspace(path) => {
-                            LoadEvent::Workspace(scan_workspace_repo(workspace_path, path))
-                        }
-                        ScanTask::Library(repo_path) => {
-                            LoadEvent::Library(scan_library_repo(library_path, repo_path))
-                        }
-                    };
-                    if tx.send(event).is_err() {
-                        break;
-                    }
-                }
-            });
-        }
-    });
+    let mut cache = cache.into_inner().unwrap_or_else(|e| e.into_inner());
+    cache.retain(&workspace_names);
+    // The cache only speeds up later runs, so failing to write it is harmless
+    let _ = cache.save(workspace_root);
+}
+
+/// Rescan the given workspace repos on worker threads, streaming results to
+/// the UI thread like [`scan_all_repos`]
+fn scan_changed_repos(workspace: &Workspace, repos: Vec<PathBuf>, tx: mpsc::Sender<LoadEvent>) {
+    let workspace_root = Path::new(&workspace.path);
+    let cache = Mutex::new(StatusCache::load(workspace_root));
+    let cancel = AtomicBool::new(false);
+    crate::scan::for_each_parallel(
+        repos,
+        crate::scan::default_workers(),
+        &cancel,
+        |path| LoadEvent::Workspace(scan_workspace_repo(&workspace.path, path, &cache)),
+        |event, _| {
+            if tx.send(event).is_err() {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        },
+    );
+
+    let cache = cache.into_inner().unwrap_or_else(|e| e.into_inner());
+    let _ = cache.save(workspace_root);
 }
 
 /// Get the configured GitHub hostname from gh CLI
@@ -1541,7 +1770,7 @@ mod tests {
 
     #[test]
     fn sync_manager_dedups_and_requeues() {
-        let mut mgr = SyncManager::new("ws".to_string());
+        let mut mgr = SyncManager::new("ws".to_string(), SyncConfig::default());
         let repo = PathBuf::from("ws/repo");
 
         mgr.request_sync(repo.clone(), false);
@@ -41,6 +41,9 @@ pub struct RepoInfo {
     pub display_name: String,
     /// Git status, or None while it hasn't been scanned yet
     pub status: Option<crate::RepoStatus>,
+    /// Detailed git state behind the status, for workspace repos that have
+    /// been scanned
+    pub state: Option<Box<crate::RepoState>>,
     /// Modification time (for sorting and display)
     pub modification_time: Option<std::time::SystemTime>,
     /// Size on disk in bytes
@@ -51,8 +54,13 @@ pub struct RepoInfo {
     pub is_submodule: bool,
     /// Whether this submodule is initialized (checked out)
     pub submodule_initialized: bool,
-    /// Path to parent repository (for submodules)
+    /// Path to parent repository (for submodules), or the main repo a linked
+    /// worktree was added from
     pub parent_repo_path: Option<PathBuf>,
+    /// Whether this repo is a linked worktree of another repo
+    pub is_worktree: bool,
+    /// Whether this is an independent repo checked out inside another repo
+    pub is_nested: bool,
 }
 
 #[derive(Clone)]
@@ -128,13 +136,25 @@ impl TreeNode {
         }
     }
 
-    /// Collect all repo paths in this subtree
+    /// Collect all repo paths in this subtree. Linked worktrees and nested
+    /// repos come before the repo they belong to, which can't be dropped
+    /// while they exist (or have unsaved work).
     pub fn collect_repo_paths(&self) -> Vec<String> {
+        let (dependents, others): (Vec<&TreeNode>, Vec<&TreeNode>) =
+            self.children.iter().partition(|child| {
+                child
+                    .repo_info
+                    .as_ref()
+                    .is_some_and(|r| r.is_worktree || r.is_nested)
+            });
         let mut paths = Vec::new();
+        for child in dependents {
+            paths.extend(child.collect_repo_paths());
+        }
         if let Some(ref repo) = self.repo_info {
             paths.push(repo.display_name.clone());
         }
-        for child in &self.children {
+        for child in others {
             paths.extend(child.collect_repo_paths());
         }
         paths
@@ -152,9 +172,22 @@ impl TreeNode {
 
 /// Build a tree structure from a flat list of repos
 pub fn build_tree(mut repos: Vec<RepoInfo>) -> Vec<TreeNode> {
-    // Separate regular repos from submodules
-    let (submodules, regular_repos): (Vec<_>, Vec<_>) =
+    // Separate regular repos from submodules, and from linked worktrees and
+    // nested repos whose parent repo is listed too
+    let (mut children, regular_repos): (Vec<_>, Vec<_>) =
         repos.drain(..).partition(|r| r.is_submodule);
+    let parent_repos: std::collections::HashSet<PathBuf> = regular_repos
+        .iter()
+        .filter(|r| !r.is_worktree)
+        .map(|r| r.path.clone())
+        .collect();
+    let (dependents, regular_repos): (Vec<_>, Vec<_>) = regular_repos.into_iter().partition(|r| {
+        (r.is_worktree || r.is_nested)
+            && r.parent_repo_path
+                .as_ref()
+                .is_some_and(|parent| parent_repos.contains(parent))
+    });
+    children.extend(dependents);
 
     // Sort regular repos by modification time (most recent first)
     let mut sorted_repos = regular_repos;
@@ -205,16 +238,19 @@ pub fn build_tree(mut repos: Vec<RepoInfo>) -> Vec<TreeNode> {
         }
     }
 
-    // Now insert submodules as children of their parent repos
-    for submodule in submodules {
-        insert_submodule_into_tree(&mut root_nodes, submodule);
+    // Now insert the rest as children of their parent repos, outermost
+    // first since nested repos can be parents themselves
+    children.sort_by_key(|r| r.parent_repo_path.as_ref().map(|p| p.components().count()));
+    for child in children {
+        insert_child_into_tree(&mut root_nodes, child);
     }
 
     root_nodes
 }
 
-/// Helper function to insert a submodule into the tree as a child of its parent repo
-fn insert_submodule_into_tree(root_nodes: &mut [TreeNode], submodule: RepoInfo) {
+/// Helper function to insert a submodule, linked worktree or nested repo into
+/// the tree as a child of its parent repo
+fn insert_child_into_tree(root_nodes: &mut [TreeNode], submodule: RepoInfo) {
     let parent_path = match submodule.parent_repo_path.clone() {
         Some(path) => path,
         None => return, // Shouldn't happen, but skip if no parent
@@ -222,12 +258,24 @@ fn insert_submodule_into_tree(root_nodes: &mut [TreeNode], submodule: RepoInfo)
 
     // Find the parent repo node and add the submodule as a child
     if let Some(parent_node) = find_repo_node_by_path(root_nodes, &parent_path) {
-        let name = submodule
-            .path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or(&submodule.display_name)
-            .to_string();
+        // A worktree can live anywhere in the workspace, so it keeps its full path
+        let name = if submodule.is_worktree {
+            submodule.display_name.clone()
+        } else if submodule.is_nested {
+            submodule
+                .path
+                .strip_prefix(&parent_path)
+                .unwrap_or(&submodule.path)
+                .display()
+                .to_string()
+        } else {
+            submodule
+                .path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or(&submodule.display_name)
+                .to_string()
+        };
 
         parent_node.children.push(TreeNode {
             name,
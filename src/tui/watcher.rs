@@ -1,13 +1,23 @@
+use crate::cache::CacheKey;
+use crate::config::{WatchConfig, WatchMode};
+use crate::discovery::Discovery;
+use crate::ignore::WorksetIgnore;
 use notify::{RecommendedWatcher, RecursiveMode, Watcher, recommended_watcher};
+use std::collections::{HashMap, HashSet};
 use std::path::{Component, Path, PathBuf};
-use std::sync::mpsc::{Receiver, TryRecvError, channel};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender, TryRecvError, channel};
 use std::time::{Duration, Instant};
+use tracing::debug;
 
 /// Filesystem activity observed by a single `FileWatcher::poll` call
 #[derive(Default)]
 pub struct WatchSignals {
-    /// Worktree files changed (debounced): the repo data should be reloaded
+    /// Something changed outside of any repo, so repos may have come or gone
+    /// (debounced): all repo data should be reloaded
     pub refresh: bool,
+    /// Repos whose worktree files changed (debounced); only these need a
+    /// rescan
+    pub changed_repos: Vec<PathBuf>,
     /// Repos whose remote-tracking refs changed, e.g. because a `git push`
     /// ran in another terminal; candidates for a remote sync check
     pub refs_changed: Vec<PathBuf>,
@@ -18,44 +28,290 @@ enum PathClass {
     Worktree,
     /// A `.git/refs/remotes` change; carries the repo root
     RemoteRefs(PathBuf),
+    /// A change to `.git/index` or `.git/HEAD`; carries the repo root
+    GitState(PathBuf),
     Ignored,
 }
 
+/// What the polling thread found
+enum Polled {
+    /// A repo's files or git state changed
+    Repo(PathBuf),
+    /// Repos appeared or disappeared
+    Repos,
+}
+
 /// A filesystem watcher with debouncing and path filtering.
 ///
 /// This watcher:
 /// - Uses notify's recommended watcher with a channel (per notify docs)
-/// - Performs debouncing on the receive side to batch rapid changes
+/// - Watches each directory on its own, leaving out the ones the workspace's
+///   `.worksetignore` (or the built-in list) excludes, and picks up new
+///   directories as they appear
+/// - Performs debouncing on the receive side to batch rapid changes,
+///   reporting worktree changes per repo
 /// - Filters out `.workset` and most `.git` internals, except
-///   `.git/refs/remotes` changes which are reported per repo
+///   `.git/refs/remotes` changes which are reported per repo; only the git
+///   directory itself and its remote refs are watched
 /// - Drains pending events after refresh to prevent feedback loops
+///
+/// Every watched directory costs one of the system's limited watches
+/// (`fs.inotify.max_user_watches` on Linux), so a large workspace can run
+/// out. [`WatchMode::Repos`] then watches only the directories leading to
+/// repos and each repo's root, index, HEAD and remote refs, missing edits
+/// below the top of a worktree until they're staged. [`WatchMode::Poll`]
+/// uses no watches at all: a background thread compares every repo's
+/// [`CacheKey`] at an interval, which misses the same edits and leaves
+/// remote ref changes to the periodic sync. Running out of watches later,
+/// for directories created while watching, leaves those unwatched and shows
+/// in [`FileWatcher::limitations`].
 pub struct FileWatcher {
-    _watcher: RecommendedWatcher,
+    mode: WatchMode,
+    /// `None` when polling
+    watcher: Option<RecommendedWatcher>,
+    root: PathBuf,
+    ignore: WorksetIgnore,
     rx: Receiver<Result<notify::Event, notify::Error>>,
     last_refresh: Instant,
     debounce_duration: Duration,
+    /// Changes seen during the debounce period, reported once it's over
+    pending_refresh: bool,
+    pending_repos: Vec<PathBuf>,
+    /// Changes found by the polling thread
+    polled: Option<Receiver<Polled>>,
+    /// Dropping this stops the polling thread
+    _stop_polling: Option<Sender<()>>,
+    poll_interval: Duration,
+    /// Whether a directory that appeared couldn't be watched for lack of
+    /// watches
+    out_of_watches: bool,
 }
 
 impl FileWatcher {
-    /// Create a new file watcher for the given path.
-    pub fn new(path: &Path, debounce_duration: Duration) -> Result<Self, notify::Error> {
+    /// Watch the given path the way the config asks, falling back to fewer
+    /// watches and then to polling when watching fails (usually because the
+    /// system's watch limit was reached)
+    pub fn start(path: &Path, debounce_duration: Duration, config: &WatchConfig) -> Self {
+        let modes: &[WatchMode] = match config.mode {
+            WatchMode::Auto => &[WatchMode::Full, WatchMode::Repos],
+            WatchMode::Full => &[WatchMode::Full],
+            WatchMode::Repos => &[WatchMode::Repos],
+            WatchMode::Poll => &[],
+        };
+        for &mode in modes {
+            match Self::watching(path, debounce_duration, mode) {
+                Ok(watcher) => return watcher,
+                Err(e) => debug!(?mode, error = %e, "Failed to watch the workspace"),
+            }
+        }
+        Self::polling(path, debounce_duration, config.poll_interval())
+    }
+
+    /// Register watches for [`WatchMode::Full`] or [`WatchMode::Repos`]
+    fn watching(
+        path: &Path,
+        debounce_duration: Duration,
+        mode: WatchMode,
+    ) -> Result<Self, notify::Error> {
         let (tx, rx) = channel();
-        let mut watcher = recommended_watcher(tx)?;
-        watcher.watch(path, RecursiveMode::Recursive)?;
+        let mut watcher = Self {
+            watcher: Some(recommended_watcher(tx)?),
+            ..Self::unwatched(path, debounce_duration, mode, rx)
+        };
+        watcher.watch(path, RecursiveMode::NonRecursive)?;
+        match mode {
+            WatchMode::Repos => watcher.watch_repos(path)?,
+            _ => watcher.watch_children(path)?,
+        }
+        Ok(watcher)
+    }
 
-        Ok(Self {
-            _watcher: watcher,
+    /// Check every repo for changes at the given interval, without watches
+    fn polling(path: &Path, debounce_duration: Duration, interval: Duration) -> Self {
+        // Nothing sends filesystem events
+        let (_, rx) = channel();
+        let (polled_tx, polled_rx) = channel();
+        let (stop_tx, stop_rx) = channel();
+        let root = path.to_path_buf();
+        std::thread::spawn(move || poll_repos(&root, interval, &polled_tx, &stop_rx));
+        Self {
+            polled: Some(polled_rx),
+            _stop_polling: Some(stop_tx),
+            poll_interval: interval,
+            ..Self::unwatched(path, debounce_duration, WatchMode::Poll, rx)
+        }
+    }
+
+    fn unwatched(
+        path: &Path,
+        debounce_duration: Duration,
+        mode: WatchMode,
+        rx: Receiver<Result<notify::Event, notify::Error>>,
+    ) -> Self {
+        Self {
+            mode,
+            watcher: None,
+            root: path.to_path_buf(),
+            ignore: WorksetIgnore::for_path(path),
             rx,
             last_refresh: Instant::now(),
             debounce_duration,
+            pending_refresh: false,
+            pending_repos: Vec::new(),
+            polled: None,
+            _stop_polling: None,
+            poll_interval: Duration::ZERO,
+            out_of_watches: false,
+        }
+    }
+
+    /// A note on what this watcher can't see, for display; `None` when it
+    /// sees everything
+    pub fn limitations(&self) -> Option<String> {
+        let limitation = match self.mode {
+            WatchMode::Repos => Some("watching repo roots only".to_string()),
+            WatchMode::Poll => Some(format!("polling every {}s", self.poll_interval.as_secs())),
+            _ => None,
+        };
+        if !self.out_of_watches {
+            return limitation;
+        }
+        let unwatched = "out of watches for new directories";
+        Some(match limitation {
+            Some(limitation) => format!("{}, {}", limitation, unwatched),
+            None => unwatched.to_string(),
         })
     }
 
+    fn watch(&mut self, path: &Path, mode: RecursiveMode) -> Result<(), notify::Error> {
+        match self.watcher.as_mut() {
+            Some(watcher) => watcher.watch(path, mode),
+            None => Ok(()),
+        }
+    }
+
+    /// Watch the repos below `dir` for [`WatchMode::Repos`]: the directories
+    /// between `dir` and each repo, so new repos are noticed, and the parts
+    /// of each repo that change with its status
+    fn watch_repos(&mut self, dir: &Path) -> Result<(), notify::Error> {
+        let repos = Discovery::for_path(dir).find_repos(dir);
+        let mut dirs = HashSet::new();
+        for repo in &repos {
+            for ancestor in repo.ancestors().skip(1) {
+                if !ancestor.starts_with(dir) || ancestor == dir || !dirs.insert(ancestor) {
+                    break;
+                }
+                self.watch_or_skip(ancestor, RecursiveMode::NonRecursive)?;
+            }
+        }
+        for repo in &repos {
+            self.watch_repo(repo)?;
+        }
+        Ok(())
+    }
+
+    /// Watch a repo's root along with [`Self::watch_git`]
+    fn watch_repo(&mut self, repo: &Path) -> Result<(), notify::Error> {
+        if repo != self.root {
+            self.watch_or_skip(repo, RecursiveMode::NonRecursive)?;
+        }
+        self.watch_git(repo)
+    }
+
+    /// Watch a repo's git directory (where git replaces the index and HEAD)
+    /// and its remote-tracking refs, but none of the objects and other
+    /// internals that churn with every git command
+    fn watch_git(&mut self, repo: &Path) -> Result<(), notify::Error> {
+        self.watch_or_skip(&crate::git_dir(repo), RecursiveMode::NonRecursive)?;
+        // Watching `refs` shows when the first fetch creates `refs/remotes`
+        let refs = crate::common_git_dir(repo).join("refs");
+        self.watch_or_skip(&refs, RecursiveMode::NonRecursive)?;
+        let remotes = refs.join("remotes");
+        if remotes.is_dir() {
+            self.watch_or_skip(&remotes, RecursiveMode::Recursive)?;
+        }
+        Ok(())
+    }
+
+    /// Watch a path, skipping it if it vanished or can't be watched; running
+    /// out of watches is an error
+    fn watch_or_skip(&mut self, path: &Path, mode: RecursiveMode) -> Result<(), notify::Error> {
+        match self.watch(path, mode) {
+            Err(e) if matches!(e.kind, notify::ErrorKind::MaxFilesWatch) => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Watch the directories beneath `dir` that aren't ignored, and the git
+    /// directories of repos among them. Symlinks aren't followed, and
+    /// directories that vanish or can't be read are skipped; running out of
+    /// watches is an error.
+    fn watch_children(&mut self, dir: &Path) -> Result<(), notify::Error> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Ok(());
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_name() == ".git" {
+                self.watch_git(dir)?;
+                continue;
+            }
+            if !entry.file_type().is_ok_and(|t| t.is_dir()) || self.ignore.is_ignored(&path) {
+                continue;
+            }
+            match self.watch(&path, RecursiveMode::NonRecursive) {
+                Ok(()) => self.watch_children(&path)?,
+                Err(e) if matches!(e.kind, notify::ErrorKind::MaxFilesWatch) => return Err(e),
+                Err(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Start watching a directory that appeared after the watcher was set up.
+    /// A `.git` turns its parent into a repo, and the first fetch creates
+    /// remote refs to watch; anything else inside one is either unwatched or
+    /// below remote refs that are watched recursively already.
+    /// Watching only repos, the only other directories to watch are those
+    /// outside of any repo. Running out of watches is an error.
+    fn watch_new_directory(&mut self, path: &Path) -> Result<(), notify::Error> {
+        if !path.is_dir() || path.is_symlink() || self.ignore.is_ignored(path) {
+            return Ok(());
+        }
+        if path.file_name().is_some_and(|name| name == ".git") {
+            return match path.parent() {
+                Some(repo) => self.watch_repo(repo),
+                None => Ok(()),
+            };
+        }
+        let in_git_dir = |levels: usize| {
+            path.ancestors()
+                .nth(levels)
+                .is_some_and(|git| git.join("HEAD").is_file())
+        };
+        if path.ends_with("refs/remotes") && in_git_dir(2) {
+            return self.watch_or_skip(path, RecursiveMode::Recursive);
+        }
+        if path.components().any(|c| c.as_os_str() == ".git") {
+            return Ok(());
+        }
+        if self.mode == WatchMode::Repos {
+            if path.join(".git").exists() {
+                self.watch_repo(path)?;
+            } else if self.repo_containing(path).is_none() {
+                self.watch_or_skip(path, RecursiveMode::NonRecursive)?;
+                self.watch_repos(path)?;
+            }
+            return Ok(());
+        }
+        self.watch_or_skip(path, RecursiveMode::NonRecursive)?;
+        self.watch_children(path)
+    }
+
     /// Drain all pending events, classify them, and apply debouncing to the
     /// refresh signal. Remote-ref changes are reported per repo, undebounced
     /// (the sync scheduler applies its own cooldown).
     pub fn poll(&mut self) -> WatchSignals {
-        let mut worktree_changed = false;
         let mut refs_changed: Vec<PathBuf> = Vec::new();
 
         loop {
@@ -65,14 +321,37 @@ impl FileWatcher {
                     if matches!(event.kind, notify::EventKind::Access(_)) {
                         continue;
                     }
+                    // Directories created or moved in need watches of their own
+                    if matches!(
+                        event.kind,
+                        notify::EventKind::Create(_)
+                            | notify::EventKind::Modify(notify::event::ModifyKind::Name(_))
+                    ) {
+                        for path in &event.paths {
+                            if let Err(e) = self.watch_new_directory(path) {
+                                debug!(path = %path.display(), error = %e, "Out of watches");
+                                self.out_of_watches = true;
+                            }
+                        }
+                    }
                     for path in &event.paths {
                         match Self::classify(path) {
-                            PathClass::Worktree => worktree_changed = true,
+                            PathClass::Worktree => match self.repo_containing(path) {
+                                Some(repo) => self.add_pending_repo(repo),
+                                None => self.pending_refresh = true,
+                            },
                             PathClass::RemoteRefs(repo_root) => {
                                 if !refs_changed.contains(&repo_root) {
                                     refs_changed.push(repo_root);
                                 }
                             }
+                            // Watching everything, worktree changes show up
+                            // directly; the index and HEAD also change when
+                            // the TUI's own scans refresh them
+                            PathClass::GitState(repo_root) if self.mode == WatchMode::Repos => {
+                                self.add_pending_repo(repo_root);
+                            }
+                            PathClass::GitState(_) => {}
                             PathClass::Ignored => {}
                         }
                     }
@@ -86,14 +365,41 @@ impl FileWatcher {
             }
         }
 
-        let refresh = worktree_changed && self.last_refresh.elapsed() > self.debounce_duration;
-        if refresh {
-            self.last_refresh = Instant::now();
+        while let Some(polled) = self.polled.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            match polled {
+                Polled::Repo(repo) => self.add_pending_repo(repo),
+                Polled::Repos => self.pending_refresh = true,
+            }
         }
-        WatchSignals {
-            refresh,
+
+        let mut signals = WatchSignals {
             refs_changed,
+            ..WatchSignals::default()
+        };
+        let changed = self.pending_refresh || !self.pending_repos.is_empty();
+        if changed && self.last_refresh.elapsed() > self.debounce_duration {
+            self.last_refresh = Instant::now();
+            signals.refresh = std::mem::take(&mut self.pending_refresh);
+            signals.changed_repos = std::mem::take(&mut self.pending_repos);
         }
+        signals
+    }
+
+    fn add_pending_repo(&mut self, repo: PathBuf) {
+        if !self.pending_repos.contains(&repo) {
+            self.pending_repos.push(repo);
+        }
+    }
+
+    /// The repo a changed path belongs to: the closest directory above it
+    /// with a `.git`. A repo directory itself appearing or disappearing
+    /// counts as a change outside of any repo.
+    fn repo_containing(&self, path: &Path) -> Option<PathBuf> {
+        path.ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root))
+            .find(|dir| dir.join(".git").exists())
+            .map(Path::to_path_buf)
     }
 
     /// Drain all pending events and discard them.
@@ -102,13 +408,17 @@ impl FileWatcher {
     /// the refresh operation itself generates filesystem events.
     pub fn drain_pending(&mut self) {
         while self.rx.try_recv().is_ok() {}
+        self.pending_refresh = false;
+        self.pending_repos.clear();
         self.last_refresh = Instant::now();
     }
 
     /// Classify a watched path. `.workset` and `.git` internals are noise,
     /// except `.git/refs/remotes/*` which signals that a push or fetch
-    /// touched the repo's remote-tracking refs. `.git/refs/heads` is
-    /// deliberately ignored: local commits shouldn't trigger network checks.
+    /// touched the repo's remote-tracking refs, and `.git/index` and
+    /// `.git/HEAD` which change with staging, commits and checkouts.
+    /// `.git/refs/heads` is deliberately ignored: local commits shouldn't
+    /// trigger network checks.
     fn classify(path: &Path) -> PathClass {
         let mut repo_root = PathBuf::new();
         let mut components = path.components();
@@ -119,12 +429,15 @@ impl FileWatcher {
                 }
                 if name == ".git" {
                     let mut rest = components.map(|c| c.as_os_str());
-                    if rest.next().is_some_and(|c| c == "refs")
-                        && rest.next().is_some_and(|c| c == "remotes")
-                    {
-                        return PathClass::RemoteRefs(repo_root);
-                    }
-                    return PathClass::Ignored;
+                    return match rest.next() {
+                        Some(c) if c == "refs" && rest.next().is_some_and(|c| c == "remotes") => {
+                            PathClass::RemoteRefs(repo_root)
+                        }
+                        Some(c) if (c == "index" || c == "HEAD") && rest.next().is_none() => {
+                            PathClass::GitState(repo_root)
+                        }
+                        _ => PathClass::Ignored,
+                    };
                 }
             }
             repo_root.push(component);
@@ -133,16 +446,53 @@ impl FileWatcher {
     }
 }
 
+/// The polling thread: compare every repo's [`CacheKey`] with the last one
+/// at each interval, until the watcher is dropped
+fn poll_repos(root: &Path, interval: Duration, tx: &Sender<Polled>, stop: &Receiver<()>) {
+    let read_keys = || -> HashMap<PathBuf, CacheKey> {
+        Discovery::for_path(root)
+            .find_repos(root)
+            .into_iter()
+            .map(|repo| {
+                let key = CacheKey::read(&repo);
+                (repo, key)
+            })
+            .collect()
+    };
+    let mut keys = read_keys();
+    while let Err(RecvTimeoutError::Timeout) = stop.recv_timeout(interval) {
+        let latest = read_keys();
+        let mut changes: Vec<Polled> = latest
+            .iter()
+            .filter(|(repo, key)| keys.get(*repo).is_some_and(|old| old != *key))
+            .map(|(repo, _)| Polled::Repo(repo.clone()))
+            .collect();
+        if latest.len() != keys.len() || latest.keys().any(|repo| !keys.contains_key(repo)) {
+            changes.push(Polled::Repos);
+        }
+        for change in changes {
+            if tx.send(change).is_err() {
+                return;
+            }
+        }
+        keys = latest;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
     use tempfile::TempDir;
 
+    fn watch_all(path: &Path, debounce_ms: u64) -> FileWatcher {
+        FileWatcher::watching(path, Duration::from_millis(debounce_ms), WatchMode::Full).unwrap()
+    }
+
     #[test]
     fn test_debouncing_prevents_rapid_refreshes() {
         let temp_dir = TempDir::new().unwrap();
-        let mut watcher = FileWatcher::new(temp_dir.path(), Duration::from_millis(100)).unwrap();
+        let mut watcher = watch_all(temp_dir.path(), 100);
 
         // Wait for debounce period to pass (watcher starts with last_refresh = now)
         std::thread::sleep(Duration::from_millis(150));
@@ -187,7 +537,7 @@ mod tests {
         let git_dir = temp_dir.path().join(".git");
         fs::create_dir(&git_dir).unwrap();
 
-        let mut watcher = FileWatcher::new(temp_dir.path(), Duration::from_millis(50)).unwrap();
+        let mut watcher = watch_all(temp_dir.path(), 50);
 
         // Create a file in .git directory
         fs::write(git_dir.join("config"), "test").unwrap();
@@ -199,12 +549,37 @@ mod tests {
         let refresh = watcher.poll().refresh;
         assert!(!refresh, ".git directory changes should be filtered");
 
-        // But regular file changes should trigger
+        // But regular file changes should trigger a rescan of the repo
         fs::write(temp_dir.path().join("regular.txt"), "test").unwrap();
         std::thread::sleep(Duration::from_millis(100));
 
-        let refresh = watcher.poll().refresh;
-        assert!(refresh, "Regular file changes should trigger refresh");
+        let signals = watcher.poll();
+        assert_eq!(signals.changed_repos, vec![temp_dir.path().to_path_buf()]);
+    }
+
+    #[test]
+    fn test_worktree_changes_report_repo() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path().join("group/repo");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(repo.join("src")).unwrap();
+
+        let mut watcher = watch_all(temp_dir.path(), 50);
+        std::thread::sleep(Duration::from_millis(100));
+
+        fs::write(repo.join("src/main.rs"), "test").unwrap();
+        fs::write(repo.join("README.md"), "test").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        let signals = watcher.poll();
+        assert!(!signals.refresh, "changes inside a repo need no reload");
+        assert_eq!(signals.changed_repos, vec![repo.clone()]);
+
+        // A new repo isn't inside any listed one, so everything is reloaded
+        fs::create_dir_all(temp_dir.path().join("group/other")).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        let signals = watcher.poll();
+        assert!(signals.refresh);
+        assert!(signals.changed_repos.is_empty());
     }
 
     #[test]
@@ -213,7 +588,7 @@ mod tests {
         let workset_dir = temp_dir.path().join(".workset");
         fs::create_dir(&workset_dir).unwrap();
 
-        let mut watcher = FileWatcher::new(temp_dir.path(), Duration::from_millis(50)).unwrap();
+        let mut watcher = watch_all(temp_dir.path(), 50);
 
         // Create a file in .workset directory
         fs::write(workset_dir.join("data"), "test").unwrap();
@@ -226,10 +601,47 @@ mod tests {
         assert!(!refresh, ".workset directory changes should be filtered");
     }
 
+    #[test]
+    fn test_ignored_directories_not_watched() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join(".workset")).unwrap();
+        fs::write(temp_dir.path().join(".worksetignore"), "scratch\n").unwrap();
+        let build_dir = temp_dir.path().join("repo/target/debug");
+        fs::create_dir_all(&build_dir).unwrap();
+        fs::create_dir_all(temp_dir.path().join("repo/.git")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("scratch")).unwrap();
+
+        let mut watcher = watch_all(temp_dir.path(), 50);
+        std::thread::sleep(Duration::from_millis(100));
+
+        fs::write(build_dir.join("out"), "test").unwrap();
+        fs::write(temp_dir.path().join("scratch/notes"), "test").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        let signals = watcher.poll();
+        assert!(
+            !signals.refresh && signals.changed_repos.is_empty(),
+            "ignored directories should not be watched"
+        );
+
+        // Directories created later are watched too
+        let new_dir = temp_dir.path().join("repo/src/nested");
+        fs::create_dir_all(&new_dir).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        watcher.poll();
+        std::thread::sleep(Duration::from_millis(100));
+        fs::write(new_dir.join("lib.rs"), "test").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(
+            watcher.poll().changed_repos,
+            vec![temp_dir.path().join("repo")],
+            "new directories should be watched"
+        );
+    }
+
     #[test]
     fn test_drain_pending_prevents_feedback_loop() {
         let temp_dir = TempDir::new().unwrap();
-        let mut watcher = FileWatcher::new(temp_dir.path(), Duration::from_millis(50)).unwrap();
+        let mut watcher = watch_all(temp_dir.path(), 50);
 
         // Create a file
         fs::write(temp_dir.path().join("test.txt"), "hello").unwrap();
@@ -263,6 +675,10 @@ mod tests {
         ));
         assert!(matches!(
             FileWatcher::classify(Path::new("/ws/repo/.git/index")),
+            PathClass::GitState(root) if root == Path::new("/ws/repo")
+        ));
+        assert!(matches!(
+            FileWatcher::classify(Path::new("/ws/repo/.git/index.lock")),
             PathClass::Ignored
         ));
         assert!(matches!(
@@ -275,13 +691,147 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_repos_mode_watches_repo_roots_and_git_state() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path().join("group/repo");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(repo.join("src")).unwrap();
+
+        let mut watcher =
+            FileWatcher::watching(temp_dir.path(), Duration::from_millis(50), WatchMode::Repos)
+                .unwrap();
+        assert_eq!(
+            watcher.limitations().as_deref(),
+            Some("watching repo roots only")
+        );
+        std::thread::sleep(Duration::from_millis(100));
+
+        // Edits below the top of the worktree aren't seen until staged
+        fs::write(repo.join("src/main.rs"), "test").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(watcher.poll().changed_repos.is_empty());
+
+        fs::write(repo.join(".git/index"), "test").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(watcher.poll().changed_repos, vec![repo.clone()]);
+
+        fs::write(repo.join("README.md"), "test").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(watcher.poll().changed_repos, vec![repo.clone()]);
+
+        // New repos are noticed, and watched from then on
+        let other = temp_dir.path().join("group/other");
+        fs::create_dir_all(&other).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(watcher.poll().refresh);
+        fs::create_dir(other.join(".git")).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        watcher.poll();
+        std::thread::sleep(Duration::from_millis(100));
+        fs::write(other.join("README.md"), "test").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(watcher.poll().changed_repos, vec![other]);
+    }
+
+    #[test]
+    fn test_polling_compares_repo_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path().join("repo");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+
+        let mut watcher = FileWatcher::start(
+            temp_dir.path(),
+            Duration::from_millis(10),
+            &WatchConfig {
+                mode: WatchMode::Poll,
+                poll_interval_secs: 1,
+            },
+        );
+        assert_eq!(watcher.limitations().as_deref(), Some("polling every 1s"));
+        std::thread::sleep(Duration::from_millis(200));
+
+        // A new file at the top of the worktree changes the repo's key
+        fs::write(repo.join("new.txt"), "test").unwrap();
+        std::thread::sleep(Duration::from_millis(1500));
+        let signals = watcher.poll();
+        assert!(!signals.refresh);
+        assert_eq!(signals.changed_repos, vec![repo]);
+
+        fs::create_dir_all(temp_dir.path().join("other/.git")).unwrap();
+        std::thread::sleep(Duration::from_millis(1500));
+        assert!(watcher.poll().refresh);
+    }
+
+    #[test]
+    fn test_git_internals_not_watched() {
+        let temp_dir = TempDir::new().unwrap();
+        let git_dir = temp_dir.path().join("repo/.git");
+        fs::create_dir_all(git_dir.join("objects/ab")).unwrap();
+        fs::create_dir_all(git_dir.join("refs/heads")).unwrap();
+        fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+
+        let mut watcher = watch_all(temp_dir.path(), 50);
+        let mut paths = Vec::new();
+        // Collect events until `target` shows up or a few seconds pass,
+        // watching new directories the way `poll` does
+        let mut wait_for = |watcher: &mut FileWatcher, target: &Path| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !paths.iter().any(|path| path == target) && Instant::now() < deadline {
+                if let Ok(Ok(event)) = watcher.rx.recv_timeout(Duration::from_millis(50)) {
+                    for path in &event.paths {
+                        watcher.watch_new_directory(path).unwrap();
+                    }
+                    paths.extend(event.paths);
+                }
+            }
+            paths.clone()
+        };
+
+        fs::write(git_dir.join("objects/ab/cdef"), "object").unwrap();
+        // The first fetch creates the remote refs, which need watching too
+        fs::create_dir_all(git_dir.join("refs/remotes")).unwrap();
+        wait_for(&mut watcher, &git_dir.join("refs/remotes"));
+        fs::create_dir(git_dir.join("refs/remotes/origin")).unwrap();
+        fs::write(git_dir.join("refs/remotes/origin/main"), "0000").unwrap();
+        let paths = wait_for(&mut watcher, &git_dir.join("refs/remotes/origin/main"));
+
+        assert!(paths.contains(&git_dir.join("refs/remotes/origin/main")));
+        assert!(
+            !paths
+                .iter()
+                .any(|path| path.starts_with(git_dir.join("objects"))
+                    || path.starts_with(git_dir.join("index"))),
+            "git internals are watched: {:?}",
+            paths
+        );
+    }
+
+    #[test]
+    fn test_running_out_of_watches_is_shown() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut watcher = watch_all(temp_dir.path(), 50);
+        assert_eq!(watcher.limitations(), None);
+
+        watcher.out_of_watches = true;
+        assert_eq!(
+            watcher.limitations().as_deref(),
+            Some("out of watches for new directories")
+        );
+        watcher.mode = WatchMode::Repos;
+        assert_eq!(
+            watcher.limitations().as_deref(),
+            Some("watching repo roots only, out of watches for new directories")
+        );
+    }
+
     #[test]
     fn test_remote_ref_change_reports_repo() {
         let temp_dir = TempDir::new().unwrap();
         let refs_dir = temp_dir.path().join("repo/.git/refs/remotes/origin");
         fs::create_dir_all(&refs_dir).unwrap();
 
-        let mut watcher = FileWatcher::new(temp_dir.path(), Duration::from_millis(50)).unwrap();
+        let mut watcher = watch_all(temp_dir.path(), 50);
 
         fs::write(refs_dir.join("main"), "0000").unwrap();
         std::thread::sleep(Duration::from_millis(100));
@@ -0,0 +1,251 @@
+//! Disk usage of repos.
+//!
+//! When a disk fills up, the question is which repos to drop and what that
+//! would free. Each repo's usage is split three ways: the files git tracks,
+//! the git directory (mostly objects), and everything else in the worktree,
+//! which is untracked or ignored and usually build output that could be
+//! deleted without dropping anything. Sizes are what the files take up on
+//! disk, and a repo's usage includes everything below it that dropping it
+//! would remove, like submodules and independent repos checked out inside it.
+
+use crate::discovery::nesting_repo;
+use crate::scan::{Progress, default_workers, for_each_parallel};
+use crate::{Workspace, find_git_repositories, git_dir};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+/// Where a repo's bytes are
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DiskUsage {
+    /// Files git tracks, including those of submodules
+    pub worktree: u64,
+    /// The git directory: objects, refs, the index and so on
+    pub git: u64,
+    /// Untracked and ignored files, like build output and dependencies
+    pub artifacts: u64,
+}
+
+impl DiskUsage {
+    pub fn total(&self) -> u64 {
+        self.worktree + self.git + self.artifacts
+    }
+}
+
+/// The disk usage of one repo in a workspace
+#[derive(Debug, Clone)]
+pub struct RepoUsage {
+    /// Path relative to the workspace root
+    pub repo: String,
+    pub path: PathBuf,
+    /// Whether the repo is checked out inside another repo, whose usage
+    /// includes it already
+    pub nested: bool,
+    pub usage: DiskUsage,
+}
+
+/// Measure a repo. Files that can't be read are left out rather than failing
+/// the whole measurement, like `du` does.
+pub fn repo_usage(repo: &Path) -> DiskUsage {
+    let tracked = Tracked::read(repo);
+    let mut usage = DiskUsage {
+        git: dir_size(&git_dir(repo)),
+        ..DiskUsage::default()
+    };
+    let Ok(entries) = std::fs::read_dir(repo) else {
+        return usage;
+    };
+    for entry in entries.flatten() {
+        // A `.git` file pointing elsewhere is measured through `git_dir`
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        tracked.measure(&entry.path(), repo, &mut usage);
+    }
+    usage
+}
+
+/// The space taken by everything below `path`, without following symlinks
+pub fn dir_size(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return allocated(&metadata);
+    }
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries.flatten().map(|entry| dir_size(&entry.path())).sum()
+}
+
+/// Measure every repo under `path` in parallel, largest first
+pub fn workspace_usage(
+    workspace: &Workspace,
+    path: &Path,
+    mut on_progress: impl FnMut(Progress),
+) -> Result<Vec<RepoUsage>> {
+    let root = Path::new(&workspace.path);
+    let mut repos = Vec::new();
+    for_each_parallel(
+        find_git_repositories(path)?,
+        default_workers(),
+        &AtomicBool::new(false),
+        |path| RepoUsage {
+            repo: path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .display()
+                .to_string(),
+            nested: nesting_repo(root, &path).is_some(),
+            usage: repo_usage(&path),
+            path,
+        },
+        |usage, progress| {
+            repos.push(usage);
+            on_progress(progress);
+        },
+    );
+    repos.sort_by(|a, b| {
+        b.usage
+            .total()
+            .cmp(&a.usage.total())
+            .then_with(|| a.repo.cmp(&b.repo))
+    });
+    Ok(repos)
+}
+
+/// Format bytes as human-readable size
+pub fn format_size(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    const GB: u64 = MB * 1024;
+
+    if bytes >= GB {
+        format!("{:.1} GB", bytes as f64 / GB as f64)
+    } else if bytes >= MB {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.1} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+#[cfg(unix)]
+fn allocated(metadata: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated(metadata: &std::fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+/// The paths in a repo's index, relative to the repo with `/` separators
+struct Tracked {
+    files: HashSet<String>,
+    /// Directories with something tracked below them
+    dirs: HashSet<String>,
+    /// Submodule checkouts, which are tracked as a whole
+    submodules: HashSet<String>,
+}
+
+impl Tracked {
+    fn read(repo: &Path) -> Self {
+        let mut tracked = Self {
+            files: HashSet::new(),
+            dirs: HashSet::new(),
+            submodules: HashSet::new(),
+        };
+        let Some(index) = gix::open(repo)
+            .ok()
+            .and_then(|repo| repo.index_or_empty().ok())
+        else {
+            return tracked;
+        };
+        for entry in index.entries() {
+            let path = entry.path(&index).to_string();
+            let mut dir = path.as_str();
+            while let Some((parent, _)) = dir.rsplit_once('/') {
+                if !tracked.dirs.insert(parent.to_string()) {
+                    break;
+                }
+                dir = parent;
+            }
+            if entry.mode.is_submodule() {
+                tracked.submodules.insert(path);
+            } else {
+                tracked.files.insert(path);
+            }
+        }
+        tracked
+    }
+
+    /// Add up one worktree entry and everything below it
+    fn measure(&self, path: &Path, repo: &Path, usage: &mut DiskUsage) {
+        let Ok(relative) = path.strip_prefix(repo) else {
+            return;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let Ok(metadata) = std::fs::symlink_metadata(path) else {
+            return;
+        };
+        if !metadata.is_dir() {
+            if self.files.contains(&relative) {
+                usage.worktree += allocated(&metadata);
+            } else {
+                usage.artifacts += allocated(&metadata);
+            }
+        } else if self.submodules.contains(&relative) {
+            usage.worktree += dir_size(path);
+        } else if !self.dirs.contains(&relative) {
+            // Nothing tracked in here, so no need to look at each file
+            usage.artifacts += dir_size(path);
+        } else if let Ok(entries) = std::fs::read_dir(path) {
+            for entry in entries.flatten() {
+                self.measure(&entry.path(), repo, usage);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::git;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn usage_is_split_by_what_git_tracks() {
+        let temp = TempDir::new().unwrap();
+        let repo = temp.path();
+        git(repo, &["init", "-q", "-b", "main"]);
+        fs::create_dir_all(repo.join("src")).unwrap();
+        fs::write(repo.join("src/main.rs"), vec![b'x'; 64 * 1024]).unwrap();
+        git(repo, &["add", "."]);
+        git(repo, &["commit", "-q", "-m", "initial"]);
+        fs::write(repo.join("src/scratch.rs"), vec![b'y'; 16 * 1024]).unwrap();
+        fs::create_dir_all(repo.join("target/debug")).unwrap();
+        fs::write(repo.join("target/debug/app"), vec![b'z'; 256 * 1024]).unwrap();
+
+        let usage = repo_usage(repo);
+        let file = |path: &str| dir_size(&repo.join(path));
+        assert_eq!(usage.worktree, file("src/main.rs"));
+        assert_eq!(usage.artifacts, file("src/scratch.rs") + file("target"));
+        assert_eq!(usage.git, file(".git"));
+        assert!(usage.artifacts >= 256 * 1024);
+        assert_eq!(usage.total(), usage.worktree + usage.git + usage.artifacts);
+    }
+
+    #[test]
+    fn sizes_are_human_readable() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GB");
+    }
+}